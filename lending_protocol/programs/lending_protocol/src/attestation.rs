@@ -0,0 +1,208 @@
+//! Credit score attestations signed off-chain by the protocol's score authority.
+//!
+//! The borrower prepends an Ed25519 precompile instruction to the transaction that
+//! verifies the authority's signature over the attestation message. The program only
+//! has to check, through the instructions sysvar, that the verified message and key
+//! are the ones it expects.
+//!
+//! Message layout (48 bytes): borrower pubkey | score as u64 LE | expires_at as i64 LE
+
+use crate::errors::Errors;
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{
+    ed25519_program,
+    sysvar::instructions::{load_current_index_checked, load_instruction_at_checked},
+};
+
+pub const ATTESTATION_MESSAGE_LEN: usize = 32 + 8 + 8;
+
+/// Offsets header of the Ed25519 precompile: count, padding, then one offsets struct
+const SIGNATURE_OFFSETS_START: usize = 2;
+const SIGNATURE_OFFSETS_LEN: usize = 14;
+const PUBKEY_LEN: usize = 32;
+
+pub struct ScoreAttestation {
+    pub borrower: Pubkey,
+    pub score: u64,
+    pub expires_at: i64,
+}
+
+/// Returns the attestation verified by the instruction right before the current one,
+/// or `None` when that instruction is not an Ed25519 signature check
+pub fn load_score_attestation(
+    instructions_sysvar: &AccountInfo,
+    score_authority: &Pubkey,
+) -> Result<Option<ScoreAttestation>> {
+    let current_index = load_current_index_checked(instructions_sysvar)?;
+    if current_index == 0 {
+        return Ok(None);
+    }
+    let ix = load_instruction_at_checked((current_index - 1) as usize, instructions_sysvar)?;
+    if ix.program_id != ed25519_program::ID {
+        return Ok(None);
+    }
+
+    parse_score_attestation(&ix.data, score_authority).map(Some)
+}
+
+/// Reads the attestation out of Ed25519 precompile instruction data, checking that the
+/// verified key is `score_authority`
+pub fn parse_score_attestation(data: &[u8], score_authority: &Pubkey) -> Result<ScoreAttestation> {
+    require!(
+        data.len() >= SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_LEN && data[0] == 1,
+        Errors::InvalidScoreAttestation
+    );
+    let read_u16 = |offset: usize| u16::from_le_bytes([data[offset], data[offset + 1]]);
+    let offsets = SIGNATURE_OFFSETS_START;
+    let signature_instruction_index = read_u16(offsets + 2);
+    let public_key_offset = read_u16(offsets + 4) as usize;
+    let public_key_instruction_index = read_u16(offsets + 6);
+    let message_data_offset = read_u16(offsets + 8) as usize;
+    let message_data_size = read_u16(offsets + 10) as usize;
+    let message_instruction_index = read_u16(offsets + 12);
+
+    // Everything the precompile verified has to live inside its own instruction data,
+    // otherwise the offsets could point at bytes we never look at
+    require!(
+        signature_instruction_index == u16::MAX
+            && public_key_instruction_index == u16::MAX
+            && message_instruction_index == u16::MAX,
+        Errors::InvalidScoreAttestation
+    );
+    require!(
+        message_data_size == ATTESTATION_MESSAGE_LEN
+            && data.len() >= public_key_offset + PUBKEY_LEN
+            && data.len() >= message_data_offset + ATTESTATION_MESSAGE_LEN,
+        Errors::InvalidScoreAttestation
+    );
+
+    let signer = Pubkey::try_from(&data[public_key_offset..public_key_offset + PUBKEY_LEN])
+        .map_err(|_| Errors::InvalidScoreAttestation)?;
+    require_keys_eq!(signer, *score_authority, Errors::InvalidScoreAttestation);
+
+    let message = &data[message_data_offset..message_data_offset + ATTESTATION_MESSAGE_LEN];
+    let borrower = Pubkey::try_from(&message[..32]).map_err(|_| Errors::InvalidScoreAttestation)?;
+    let score = u64::from_le_bytes(message[32..40].try_into().unwrap());
+    let expires_at = i64::from_le_bytes(message[40..48].try_into().unwrap());
+
+    Ok(ScoreAttestation {
+        borrower,
+        score,
+        expires_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUBKEY_OFFSET: usize = SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_LEN;
+    const SIGNATURE_OFFSET: usize = PUBKEY_OFFSET + PUBKEY_LEN;
+    const MESSAGE_OFFSET: usize = SIGNATURE_OFFSET + 64;
+
+    fn message(borrower: &Pubkey, score: u64, expires_at: i64) -> Vec<u8> {
+        let mut message = borrower.to_bytes().to_vec();
+        message.extend_from_slice(&score.to_le_bytes());
+        message.extend_from_slice(&expires_at.to_le_bytes());
+        message
+    }
+
+    /// Precompile data for one signature laid out like `new_ed25519_instruction` does:
+    /// header, key, signature, message, with every offset inside this instruction
+    fn precompile_data(signer: &Pubkey, message: &[u8]) -> Vec<u8> {
+        let mut data = vec![1, 0];
+        for value in [
+            SIGNATURE_OFFSET as u16,
+            u16::MAX,
+            PUBKEY_OFFSET as u16,
+            u16::MAX,
+            MESSAGE_OFFSET as u16,
+            message.len() as u16,
+            u16::MAX,
+        ] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(&signer.to_bytes());
+        data.extend_from_slice(&[0u8; 64]);
+        data.extend_from_slice(message);
+        data
+    }
+
+    fn set_u16(data: &mut [u8], offset: usize, value: u16) {
+        data[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn is_invalid(result: Result<ScoreAttestation>) -> bool {
+        match result {
+            Err(Error::AnchorError(error)) => {
+                error.error_code_number
+                    == Errors::InvalidScoreAttestation as u32 + anchor_lang::error::ERROR_CODE_OFFSET
+            }
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn parses_valid_attestation() {
+        let authority = Pubkey::new_unique();
+        let borrower = Pubkey::new_unique();
+        let data = precompile_data(&authority, &message(&borrower, 750, 1_700_000_000));
+
+        let attestation = parse_score_attestation(&data, &authority).unwrap();
+        assert_eq!(attestation.borrower, borrower);
+        assert_eq!(attestation.score, 750);
+        assert_eq!(attestation.expires_at, 1_700_000_000);
+    }
+
+    #[test]
+    fn rejects_other_signer() {
+        let data = precompile_data(&Pubkey::new_unique(), &message(&Pubkey::new_unique(), 750, 0));
+        assert!(is_invalid(parse_score_attestation(&data, &Pubkey::new_unique())));
+    }
+
+    #[test]
+    fn rejects_offsets_into_other_instructions() {
+        let authority = Pubkey::new_unique();
+        let data = precompile_data(&authority, &message(&Pubkey::new_unique(), 750, 0));
+        // Signature, public key and message instruction indexes
+        for index_offset in [2, 6, 12] {
+            let mut data = data.clone();
+            set_u16(&mut data, SIGNATURE_OFFSETS_START + index_offset, 0);
+            assert!(is_invalid(parse_score_attestation(&data, &authority)));
+        }
+    }
+
+    #[test]
+    fn rejects_short_or_truncated_message() {
+        let authority = Pubkey::new_unique();
+        let full = message(&Pubkey::new_unique(), 750, 0);
+
+        // Signed message shorter than an attestation
+        let data = precompile_data(&authority, &full[..ATTESTATION_MESSAGE_LEN - 1]);
+        assert!(is_invalid(parse_score_attestation(&data, &authority)));
+
+        // Offsets claim a full message but the data ends early
+        let mut data = precompile_data(&authority, &full);
+        data.truncate(data.len() - 1);
+        assert!(is_invalid(parse_score_attestation(&data, &authority)));
+
+        // Key offset past the end of the data
+        let mut data = precompile_data(&authority, &full);
+        let len = data.len() as u16;
+        set_u16(&mut data, SIGNATURE_OFFSETS_START + 4, len - 16);
+        assert!(is_invalid(parse_score_attestation(&data, &authority)));
+
+        // Not even a complete offsets header
+        assert!(is_invalid(parse_score_attestation(&[1, 0, 0], &authority)));
+    }
+
+    #[test]
+    fn rejects_other_signature_counts() {
+        let authority = Pubkey::new_unique();
+        let mut data = precompile_data(&authority, &message(&Pubkey::new_unique(), 750, 0));
+        for count in [0, 2] {
+            data[0] = count;
+            assert!(is_invalid(parse_score_attestation(&data, &authority)));
+        }
+    }
+}
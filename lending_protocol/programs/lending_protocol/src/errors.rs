@@ -38,4 +38,118 @@ pub enum Errors {
     MissingBorrowerTokenAccount,
     #[msg("Missing borrower repay token account.")]
     MissingBorrowerRepayTokenAccount,
+    #[msg("Loan is not overdue yet")]
+    LoanNotOverdue,
+    #[msg("Loan already defaulted")]
+    LoanAlreadyDefaulted,
+    #[msg("Loan offer has already been taken")]
+    OfferAlreadyTaken,
+    #[msg("Loan offer expiry must be in the future")]
+    InvalidOfferExpiry,
+    #[msg("Loan offer has not expired yet")]
+    LoanOfferNotExpired,
+    #[msg("Ticket sizes must satisfy 0 < min_ticket <= max_ticket <= loan_amount")]
+    InvalidTicketSize,
+    #[msg("Interest rate is above the borrower's maximum")]
+    InterestRateAboveRequestMax,
+    #[msg("Credit score attestation is malformed or not signed by the score authority")]
+    InvalidScoreAttestation,
+    #[msg("Credit score attestation has expired")]
+    ScoreAttestationExpired,
+    #[msg("New loan offers are paused")]
+    OffersPaused,
+    #[msg("Mint is not allowed by the protocol config")]
+    MintNotAllowed,
+    #[msg("Mint is already allowed")]
+    MintAlreadyAllowed,
+    #[msg("Allowed mints list is full")]
+    AllowedMintsFull,
+    #[msg("Loan amount is above the protocol limit")]
+    LoanAmountAboveLimit,
+    #[msg("Loan duration is above the protocol limit")]
+    DurationAboveLimit,
+    #[msg("Protocol fee must be at most 10000 bps")]
+    InvalidProtocolFee,
+    #[msg("Signer is not the pending admin")]
+    NotPendingAdmin,
+    #[msg("Treasury token account is required for SPL loans")]
+    MissingTreasuryTokenAccount,
+    #[msg("Treasury balance is too low")]
+    InsufficientTreasuryBalance,
+    #[msg("New borrows are paused")]
+    BorrowsPaused,
+    #[msg("Mint is paused")]
+    MintPaused,
+    #[msg("Mint is already paused")]
+    MintAlreadyPaused,
+    #[msg("Mint is not paused")]
+    MintNotPaused,
+    #[msg("Paused mints list is full")]
+    PausedMintsFull,
+    #[msg("Loan offer was cancelled")]
+    OfferCancelled,
+    #[msg("Loan was already liquidated")]
+    LoanAlreadyLiquidated,
+    #[msg("Loan was already refinanced")]
+    LoanAlreadyRefinanced,
+    #[msg("Invalid status transition")]
+    InvalidStatusTransition,
+    #[msg("Missing SOL collateral escrow account")]
+    MissingSolEscrow,
+    #[msg("Missing lender token account.")]
+    MissingLenderTokenAccount,
+    #[msg("Missing temporary WSOL account to unwrap into.")]
+    MissingUnwrapAccount,
+    #[msg("Non-transferable mints are not supported")]
+    NonTransferableMint,
+    #[msg("Mints with a permanent delegate are not supported")]
+    PermanentDelegateMint,
+    #[msg("Mint uses an unsupported Token-2022 extension")]
+    UnsupportedMintExtension,
+    #[msg("Invalid LTV settings")]
+    InvalidLtvSettings,
+    #[msg("Missing price feed or oracle account")]
+    MissingPriceFeed,
+    #[msg("Oracle account does not match the configured price feed")]
+    PriceFeedMismatch,
+    #[msg("Oracle account is not a valid price account")]
+    InvalidOracleAccount,
+    #[msg("Oracle price is unavailable")]
+    OraclePriceUnavailable,
+    #[msg("Loan to value exceeds the offer's maximum")]
+    LoanToValueTooHigh,
+    #[msg("Loan has price liquidation disabled")]
+    LiquidationDisabled,
+    #[msg("Loan is above the liquidation threshold")]
+    LoanHealthy,
+    #[msg("Missing liquidator token account.")]
+    MissingLiquidatorTokenAccount,
+    #[msg("Invalid oracle limits")]
+    InvalidOracleLimits,
+    #[msg("Oracle price is too old")]
+    StalePrice,
+    #[msg("Oracle confidence interval is too wide")]
+    PriceConfidenceTooWide,
+    #[msg("Not enough oracle sources returned a usable price")]
+    NotEnoughPriceSources,
+    #[msg("A price feed takes one to three oracle sources")]
+    InvalidPriceSourceCount,
+    #[msg("Invalid liquidation parameters")]
+    InvalidLiquidationParams,
+    #[msg("Loan is below the liquidation threshold")]
+    LoanNotLiquidatable,
+    #[msg("Invalid auction parameters")]
+    InvalidAuctionParams,
+    #[msg("Auction price is above the bid's maximum")]
+    AuctionPriceAboveMax,
+    #[msg("Missing bidder token account.")]
+    MissingBidderTokenAccount,
+    #[msg("Loan offer is still open")]
+    OfferStillOpen,
+    #[msg("Loans drawn from the offer are still outstanding")]
+    OfferHasOutstandingLoans,
+    #[msg("Signer is not the program's upgrade authority")]
+    NotUpgradeAuthority,
+    #[msg("Credit score attestation is required while a score authority is configured")]
+    MissingScoreAttestation,
 }
@@ -0,0 +1,32 @@
+use anchor_lang::prelude::*;
+
+#[event]
+pub struct CollateralAdded {
+    pub open_loan: Pubkey,
+    pub borrower: Pubkey,
+    pub token_mint: Pubkey,
+    pub amount: u64,
+    pub total_collateral: u64,
+}
+
+#[event]
+pub struct LoanLiquidated {
+    pub open_loan: Pubkey,
+    pub liquidator: Pubkey,
+    pub debt_repaid: u64,
+    pub collateral_seized: u64,
+    pub remaining_principal: u64,
+    pub bad_debt: u64,          // Principal written off when the collateral ran out first
+    pub ltv_bps: u64,           // Loan to value the liquidation was allowed at
+}
+
+#[event]
+pub struct AuctionSettled {
+    pub auction: Pubkey,
+    pub open_loan: Pubkey,
+    pub bidder: Pubkey,
+    pub price: u64,
+    pub lender_proceeds: u64,
+    pub borrower_surplus: u64,
+    pub collateral_amount: u64,
+}
@@ -0,0 +1,125 @@
+use crate::{
+    errors::Errors,
+    events::CollateralAdded,
+    state::{loan::{OpenLoan, LoanInfo}, collateral::CollateralVault, config::ProtocolConfig},
+    token_utils::transfer_tokens,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use std::str::FromStr;
+
+pub fn add_collateral(ctx: Context<AddCollateral>, amount: u64) -> Result<()> {
+    require!(amount > 0, Errors::InvalidCollateralAmount);
+    ctx.accounts.open_loan.require_active()?;
+
+    // The loaner wants sol as collateral
+    let deposited = if ctx.accounts.loan_info.collateral_token_mint == Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap() {
+        let sol_escrow = ctx.accounts.sol_escrow.as_ref().ok_or(Errors::MissingSolEscrow)?;
+        let ix = solana_program::system_instruction::transfer(
+            &ctx.accounts.borrower.key(),
+            &sol_escrow.key(),
+            amount,
+        );
+        solana_program::program::invoke(
+            &ix,
+            &[
+                ctx.accounts.borrower.to_account_info(),
+                sol_escrow.to_account_info(),
+            ],
+        )?;
+        amount
+    } else {
+        // The loaner wants tokens as collateral
+        let borrower_token_account = ctx.accounts.borrower_token_account
+            .as_ref()
+            .ok_or(Errors::MissingBorrowerTokenAccount)?;
+        let collateral_token_account = ctx.accounts.collateral_token_account
+            .as_ref()
+            .ok_or(Errors::MissingCollateralVaultTokenAccount)?;
+        transfer_tokens(
+            &borrower_token_account.to_account_info(),
+            &collateral_token_account.to_account_info(),
+            &ctx.accounts.collateral_token_mint,
+            &ctx.accounts.borrower.to_account_info(),
+            &ctx.accounts.token_program,
+            amount,
+            &[],
+        )?
+    };
+
+    // Only the collateral balance changes, the repayment terms stay as they are
+    let collateral_vault = &mut ctx.accounts.collateral_vault;
+    collateral_vault.amount = collateral_vault.amount.checked_add(deposited).ok_or(Errors::MathOverflow)?;
+
+    emit!(CollateralAdded {
+        open_loan: ctx.accounts.open_loan.key(),
+        borrower: ctx.accounts.borrower.key(),
+        token_mint: collateral_vault.token_mint,
+        amount: deposited,
+        total_collateral: collateral_vault.amount,
+    });
+
+    msg!(
+        "Borrower {} added {} collateral, {} now locked",
+        ctx.accounts.borrower.key(),
+        deposited,
+        collateral_vault.amount
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct AddCollateral<'info> {
+    #[account(
+        seeds = [b"open_loan", loan_info.key().as_ref(), borrower.key().as_ref()],
+        bump = open_loan.bump
+    )]
+    pub open_loan: Account<'info, OpenLoan>,
+
+    pub loan_info: Account<'info, LoanInfo>,
+
+    #[account(
+        mut,
+        seeds = [b"collateral_vault", loan_info.key().as_ref(), borrower.key().as_ref()],
+        bump = collateral_vault.bump
+    )]
+    pub collateral_vault: Account<'info, CollateralVault>,
+
+    #[account(
+        mut,
+        token::mint = collateral_token_mint,
+        token::authority = borrower
+    )]
+    /// Funds the added SPL collateral, unused for SOL collateral
+    pub borrower_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        token::authority = collateral_vault,
+        seeds = [b"collateral_token_account", collateral_vault.key().as_ref()],
+        bump
+    )]
+    /// Holds SPL collateral for the loan, unused for SOL collateral
+    pub collateral_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"sol_escrow", collateral_vault.key().as_ref()],
+        bump
+    )]
+    /// System owned PDA holding SOL collateral, unused for SPL collateral
+    pub sol_escrow: Option<SystemAccount<'info>>,
+
+    #[account(address = loan_info.collateral_token_mint)]
+    pub collateral_token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(seeds = [b"protocol_config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
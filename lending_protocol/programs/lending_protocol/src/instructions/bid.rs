@@ -0,0 +1,273 @@
+use crate::{
+    errors::Errors,
+    events::AuctionSettled,
+    instructions::take_loan::transfer_from_sol_escrow,
+    state::{auction::Auction, collateral::CollateralVault, config::ProtocolConfig, loan::{LoanInfo, OpenLoan}},
+    token_utils::{amount_with_fee, transfer_tokens},
+};
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use anchor_spl::token_interface::{close_account, CloseAccount, Mint, TokenAccount, TokenInterface};
+use std::str::FromStr;
+
+/// Buys the whole collateral lot at the auction's current price, which has to be at most
+/// `max_price`. The lender gets the proceeds up to the amount owed and the borrower gets
+/// anything above that.
+pub fn bid(ctx: Context<Bid>, max_price: u64) -> Result<()> {
+    let clock = Clock::get()?;
+    let auction = &ctx.accounts.auction;
+    let price = auction.current_price(clock.unix_timestamp);
+    require!(price <= max_price, Errors::AuctionPriceAboveMax);
+
+    let lender_proceeds = price.min(auction.amount_owed);
+    let borrower_surplus = price - lender_proceeds;
+    let collateral_amount = ctx.accounts.collateral_vault.amount;
+
+    // Pay for the lot
+    let is_loaned_sol = ctx.accounts.loan_info.loan_token_mint == Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap();
+    for (amount, recipient, recipient_token_account, missing_account) in [
+        (
+            lender_proceeds,
+            &ctx.accounts.lender,
+            ctx.accounts.lender_token_account.as_ref(),
+            Errors::MissingLenderTokenAccount,
+        ),
+        (
+            borrower_surplus,
+            &ctx.accounts.borrower,
+            ctx.accounts.borrower_loan_token_account.as_ref(),
+            Errors::MissingBorrowerTokenAccount,
+        ),
+    ] {
+        if amount == 0 {
+            continue;
+        }
+        if is_loaned_sol {
+            let cpi_accounts = system_program::Transfer {
+                from: ctx.accounts.bidder.to_account_info(),
+                to: recipient.clone(),
+            };
+            let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+            system_program::transfer(cpi_ctx, amount)?;
+        } else {
+            let bidder_loan_token_account = ctx.accounts.bidder_loan_token_account
+                .as_ref()
+                .ok_or(Errors::MissingBidderTokenAccount)?;
+            let recipient_token_account = recipient_token_account.ok_or(missing_account)?;
+            let received = transfer_tokens(
+                &bidder_loan_token_account.to_account_info(),
+                &recipient_token_account.to_account_info(),
+                &ctx.accounts.loan_token_mint,
+                &ctx.accounts.bidder.to_account_info(),
+                &ctx.accounts.token_program,
+                amount_with_fee(&ctx.accounts.loan_token_mint, amount)?,
+                &[],
+            )?;
+            require!(received >= amount, Errors::InsufficientRepayment);
+        }
+    }
+
+    // Hand over the collateral
+    let is_collateral_sol = ctx.accounts.loan_info.collateral_token_mint == Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap();
+    if is_collateral_sol {
+        // The rent reserve of the escrow goes back to the borrower
+        let sol_escrow = ctx.accounts.sol_escrow.as_ref().ok_or(Errors::MissingSolEscrow)?;
+        let collateral_vault = ctx.accounts.collateral_vault.key();
+        let bump = ctx.bumps.sol_escrow.ok_or(Errors::MissingSolEscrow)?;
+        if collateral_amount > 0 {
+            transfer_from_sol_escrow(
+                sol_escrow,
+                &ctx.accounts.bidder.to_account_info(),
+                &ctx.accounts.system_program,
+                &collateral_vault,
+                bump,
+                collateral_amount,
+            )?;
+        }
+        transfer_from_sol_escrow(
+            sol_escrow,
+            &ctx.accounts.borrower,
+            &ctx.accounts.system_program,
+            &collateral_vault,
+            bump,
+            sol_escrow.lamports(),
+        )?;
+    } else {
+        let loan_info = ctx.accounts.loan_info.key();
+        let borrower = ctx.accounts.borrower.key();
+        let seeds = &[
+            b"collateral_vault",
+            loan_info.as_ref(),
+            borrower.as_ref(),
+            &[ctx.accounts.collateral_vault.bump],
+        ];
+        let signer = &[&seeds[..]];
+        let collateral_token_account = ctx.accounts.collateral_token_account
+            .as_ref()
+            .ok_or(Errors::MissingCollateralVaultTokenAccount)?;
+        if collateral_amount > 0 {
+            let bidder_collateral_token_account = ctx.accounts.bidder_collateral_token_account
+                .as_ref()
+                .ok_or(Errors::MissingBidderTokenAccount)?;
+            transfer_tokens(
+                &collateral_token_account.to_account_info(),
+                &bidder_collateral_token_account.to_account_info(),
+                &ctx.accounts.collateral_token_mint,
+                &ctx.accounts.collateral_vault.to_account_info(),
+                &ctx.accounts.token_program,
+                collateral_amount,
+                signer,
+            )?;
+        }
+
+        let cpi_accounts = CloseAccount {
+            account: collateral_token_account.to_account_info(),
+            destination: ctx.accounts.borrower.to_account_info(),
+            authority: ctx.accounts.collateral_vault.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, signer);
+        close_account(cpi_ctx)?;
+    }
+
+    ctx.accounts.loan_info.outstanding_principal = ctx.accounts.loan_info.outstanding_principal
+        .checked_sub(ctx.accounts.open_loan.outstanding_principal)
+        .ok_or(Errors::MathOverflow)?;
+    ctx.accounts.collateral_vault.amount = 0;
+
+    emit!(AuctionSettled {
+        auction: ctx.accounts.auction.key(),
+        open_loan: ctx.accounts.open_loan.key(),
+        bidder: ctx.accounts.bidder.key(),
+        price,
+        lender_proceeds,
+        borrower_surplus,
+        collateral_amount,
+    });
+
+    msg!(
+        "Auction settled: Bidder {} bought {} collateral for {}, lender received {} of {} owed, borrower received {}",
+        ctx.accounts.bidder.key(),
+        collateral_amount,
+        price,
+        lender_proceeds,
+        ctx.accounts.auction.amount_owed,
+        borrower_surplus
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Bid<'info> {
+    #[account(
+        mut,
+        close = started_by,
+        has_one = open_loan,
+        has_one = started_by,
+        seeds = [b"auction", open_loan.key().as_ref()],
+        bump = auction.bump
+    )]
+    pub auction: Box<Account<'info, Auction>>,
+
+    #[account(
+        seeds = [b"open_loan", loan_info.key().as_ref(), borrower.key().as_ref()],
+        bump = open_loan.bump
+    )]
+    pub open_loan: Account<'info, OpenLoan>,
+
+    #[account(
+        mut,
+        has_one = lender,
+        has_one = loan_token_mint,
+        has_one = collateral_token_mint,
+        seeds = [
+            b"loan_info",
+            lender.key().as_ref(),
+            loan_token_mint.key().as_ref(),
+            &loan_info.offer_id.to_le_bytes()
+        ],
+        bump = loan_info.bump
+    )]
+    pub loan_info: Box<Account<'info, LoanInfo>>,
+
+    #[account(
+        mut,
+        close = borrower,
+        seeds = [b"collateral_vault", loan_info.key().as_ref(), borrower.key().as_ref()],
+        bump = collateral_vault.bump
+    )]
+    pub collateral_vault: Account<'info, CollateralVault>,
+
+    #[account(
+        mut,
+        token::authority = collateral_vault,
+        seeds = [b"collateral_token_account", collateral_vault.key().as_ref()],
+        bump
+    )]
+    /// Holds SPL collateral for the loan, unused for SOL collateral
+    pub collateral_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds = [b"sol_escrow", collateral_vault.key().as_ref()],
+        bump
+    )]
+    /// System owned PDA holding SOL collateral, unused for SPL collateral
+    pub sol_escrow: Option<SystemAccount<'info>>,
+
+    #[account(
+        mut,
+        token::mint = loan_token_mint,
+        token::authority = bidder
+    )]
+    /// Pays for the lot, unused when the loan is native SOL
+    pub bidder_loan_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        token::mint = collateral_token_mint,
+        token::authority = bidder
+    )]
+    /// Receives the SPL collateral, unused for SOL collateral
+    pub bidder_collateral_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        token::mint = loan_token_mint,
+        token::authority = lender
+    )]
+    /// Receives the proceeds owed to the lender, unused when the loan is native SOL
+    pub lender_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        token::mint = loan_token_mint,
+        token::authority = borrower
+    )]
+    /// Receives any surplus, unused when the loan is native SOL or there is no surplus
+    pub borrower_loan_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub loan_token_mint: Box<InterfaceAccount<'info, Mint>>,
+    pub collateral_token_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(seeds = [b"protocol_config"], bump = config.bump)]
+    pub config: Box<Account<'info, ProtocolConfig>>,
+
+    #[account(mut)]
+    pub bidder: Signer<'info>,
+
+    #[account(mut)]
+    /// CHECK: Receives native SOL proceeds, checked against the offer
+    pub lender: AccountInfo<'info>,
+
+    #[account(mut, constraint = open_loan.borrower == borrower.key())]
+    /// CHECK: Receives the surplus and the collateral vault rent, checked against the open loan
+    pub borrower: AccountInfo<'info>,
+
+    #[account(mut)]
+    /// CHECK: Refunded the auction rent, checked against the auction
+    pub started_by: AccountInfo<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
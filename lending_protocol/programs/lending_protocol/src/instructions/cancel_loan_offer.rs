@@ -0,0 +1,143 @@
+use crate::{
+    errors::Errors,
+    state::{loan::{LoanInfo, OfferStatus}, config::ProtocolConfig},
+    token_utils::transfer_tokens,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{close_account, CloseAccount, Mint, TokenAccount, TokenInterface};
+use std::str::FromStr;
+
+pub fn cancel_loan_offer(ctx: Context<CancelLoanOffer>) -> Result<()> {
+    ctx.accounts.loan_info.require_open()?;
+
+    let refund_amount = refund_and_close_vault(
+        &ctx.accounts.loan_info,
+        &ctx.accounts.vault,
+        ctx.accounts.lender_token_account.as_ref(),
+        &ctx.accounts.loan_token_mint,
+        &ctx.accounts.lender.to_account_info(),
+        &ctx.accounts.token_program,
+    )?;
+
+    msg!(
+        "Loan offer {} cancelled: refunded {} to lender {}",
+        ctx.accounts.loan_info.key(),
+        refund_amount,
+        ctx.accounts.lender.key()
+    );
+
+    close_offer(
+        &mut ctx.accounts.loan_info,
+        &ctx.accounts.lender.to_account_info(),
+        OfferStatus::Cancelled,
+    )?;
+
+    Ok(())
+}
+
+/// Sends the vault balance back to the lender and closes the vault, returning the refunded amount.
+/// A WSOL vault is refunded as native SOL by closing it straight to the lender.
+pub(crate) fn refund_and_close_vault<'info>(
+    loan_info: &Account<'info, LoanInfo>,
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    lender_token_account: Option<&InterfaceAccount<'info, TokenAccount>>,
+    loan_token_mint: &InterfaceAccount<'info, Mint>,
+    lender: &AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<u64> {
+    let offer_id = loan_info.offer_id.to_le_bytes();
+    let seeds = &[
+        b"loan_info",
+        loan_info.lender.as_ref(),
+        loan_info.loan_token_mint.as_ref(),
+        offer_id.as_ref(),
+        &[loan_info.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    // Refund whatever is left in the vault to the lender
+    let refund_amount = vault.amount;
+    let is_loaned_sol = loan_info.loan_token_mint == Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap();
+    if refund_amount > 0 && !is_loaned_sol {
+        let lender_token_account = lender_token_account.ok_or(Errors::MissingLenderTokenAccount)?;
+        transfer_tokens(
+            &vault.to_account_info(),
+            &lender_token_account.to_account_info(),
+            loan_token_mint,
+            &loan_info.to_account_info(),
+            token_program,
+            refund_amount,
+            signer,
+        )?;
+    }
+
+    // Close the vault so its rent goes back to the lender
+    let cpi_accounts = CloseAccount {
+        account: vault.to_account_info(),
+        destination: lender.clone(),
+        authority: loan_info.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer);
+    close_account(cpi_ctx)?;
+
+    Ok(refund_amount)
+}
+
+/// Stops further draws and closes the offer if no drawn loan still references it, otherwise
+/// the lender reclaims the rent with `close_loan_offer` once those loans are settled
+pub(crate) fn close_offer<'info>(
+    loan_info: &mut Account<'info, LoanInfo>,
+    lender: &AccountInfo<'info>,
+    status: OfferStatus,
+) -> Result<()> {
+    loan_info.transition(status)?;
+    loan_info.remaining_amount = 0;
+    if loan_info.outstanding_principal == 0 {
+        loan_info.close(lender.clone())?;
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CancelLoanOffer<'info> {
+    #[account(
+        mut,
+        has_one = lender,
+        seeds = [
+            b"loan_info",
+            lender.key().as_ref(),
+            loan_token_mint.key().as_ref(),
+            &loan_info.offer_id.to_le_bytes()
+        ],
+        bump = loan_info.bump
+    )]
+    /// Stores metadata about the loan info
+    pub loan_info: Account<'info, LoanInfo>,
+
+    #[account(
+        mut,
+        token::mint = loan_token_mint,
+        token::authority = loan_info,
+        seeds = [b"vault", loan_info.key().as_ref()],
+        bump
+    )]
+    /// Holds the tokens sent out for a loan prior to a second party borrowing
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(seeds = [b"protocol_config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    #[account(
+       mut,
+       token::mint = loan_token_mint,
+       token::authority = lender
+    )]
+    /// Receives the refund, unused when lending native SOL
+    pub lender_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub loan_token_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
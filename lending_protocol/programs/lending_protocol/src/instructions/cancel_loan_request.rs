@@ -0,0 +1,98 @@
+use crate::{
+    errors::Errors,
+    state::{loan_request::LoanRequest, config::ProtocolConfig},
+    token_utils::transfer_tokens,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{close_account, CloseAccount, Mint, TokenAccount, TokenInterface};
+use std::str::FromStr;
+
+pub fn cancel_loan_request(ctx: Context<CancelLoanRequest>) -> Result<()> {
+    let collateral_amount = ctx.accounts.loan_request.collateral_amount;
+
+    // SOL collateral sits on the request account itself and is returned when it closes
+    if ctx.accounts.collateral_token_mint.key() != Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap() {
+        let collateral_escrow = ctx.accounts.collateral_escrow
+            .as_ref()
+            .ok_or(Errors::MissingCollateralVaultTokenAccount)?;
+        let borrower_token_account = ctx.accounts.borrower_collateral_token_account
+            .as_ref()
+            .ok_or(Errors::MissingBorrowerTokenAccount)?;
+        let borrower = ctx.accounts.borrower.key();
+        let request_id = ctx.accounts.loan_request.request_id.to_le_bytes();
+        let seeds = &[
+            b"loan_request",
+            borrower.as_ref(),
+            request_id.as_ref(),
+            &[ctx.accounts.loan_request.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        transfer_tokens(
+            &collateral_escrow.to_account_info(),
+            &borrower_token_account.to_account_info(),
+            &ctx.accounts.collateral_token_mint,
+            &ctx.accounts.loan_request.to_account_info(),
+            &ctx.accounts.token_program,
+            collateral_escrow.amount,
+            signer,
+        )?;
+
+        let cpi_accounts = CloseAccount {
+            account: collateral_escrow.to_account_info(),
+            destination: ctx.accounts.borrower.to_account_info(),
+            authority: ctx.accounts.loan_request.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        close_account(cpi_ctx)?;
+    }
+
+    msg!(
+        "Loan request {} cancelled: returned {} collateral to borrower {}",
+        ctx.accounts.loan_request.key(),
+        collateral_amount,
+        ctx.accounts.borrower.key()
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CancelLoanRequest<'info> {
+    #[account(
+        mut,
+        close = borrower,
+        has_one = borrower,
+        has_one = collateral_token_mint,
+        seeds = [b"loan_request", borrower.key().as_ref(), &loan_request.request_id.to_le_bytes()],
+        bump = loan_request.bump
+    )]
+    /// Stores the terms the borrower is asking for
+    pub loan_request: Account<'info, LoanRequest>,
+
+    #[account(
+        mut,
+        token::mint = collateral_token_mint,
+        token::authority = loan_request,
+        seeds = [b"request_escrow", loan_request.key().as_ref()],
+        bump
+    )]
+    /// Holds SPL collateral until the request is filled or cancelled, unused for SOL collateral
+    pub collateral_escrow: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(seeds = [b"protocol_config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    #[account(
+       mut,
+       token::mint = collateral_token_mint,
+       token::authority = borrower
+    )]
+    pub borrower_collateral_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub collateral_token_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
@@ -0,0 +1,82 @@
+use crate::{
+    errors::Errors,
+    instructions::cancel_loan_offer::{close_offer, refund_and_close_vault},
+    state::{loan::{LoanInfo, OfferStatus}, config::ProtocolConfig},
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+/// Permissionless clean up of an untaken offer whose expiry has passed
+pub fn close_expired_loan_offer(ctx: Context<CloseExpiredLoanOffer>) -> Result<()> {
+    ctx.accounts.loan_info.require_open()?;
+
+    let clock = Clock::get()?;
+    require!(
+        clock.unix_timestamp > ctx.accounts.loan_info.expires_at,
+        Errors::LoanOfferNotExpired
+    );
+
+    let refund_amount = refund_and_close_vault(
+        &ctx.accounts.loan_info,
+        &ctx.accounts.vault,
+        ctx.accounts.lender_token_account.as_ref(),
+        &ctx.accounts.loan_token_mint,
+        &ctx.accounts.lender,
+        &ctx.accounts.token_program,
+    )?;
+
+    msg!(
+        "Expired loan offer {} closed: refunded {} to lender {}",
+        ctx.accounts.loan_info.key(),
+        refund_amount,
+        ctx.accounts.lender.key()
+    );
+
+    close_offer(&mut ctx.accounts.loan_info, &ctx.accounts.lender, OfferStatus::Expired)?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CloseExpiredLoanOffer<'info> {
+    #[account(
+        mut,
+        has_one = lender,
+        seeds = [
+            b"loan_info",
+            lender.key().as_ref(),
+            loan_token_mint.key().as_ref(),
+            &loan_info.offer_id.to_le_bytes()
+        ],
+        bump = loan_info.bump
+    )]
+    /// Stores metadata about the loan info
+    pub loan_info: Account<'info, LoanInfo>,
+
+    #[account(
+        mut,
+        token::mint = loan_token_mint,
+        token::authority = loan_info,
+        seeds = [b"vault", loan_info.key().as_ref()],
+        bump
+    )]
+    /// Holds the tokens sent out for a loan prior to a second party borrowing
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(seeds = [b"protocol_config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+
+    #[account(mut)]
+    /// CHECK: Receives the refund and rent, checked against loan_info.lender
+    pub lender: AccountInfo<'info>,
+
+    #[account(
+       mut,
+       token::mint = loan_token_mint,
+       token::authority = lender
+    )]
+    /// Receives the refund, unused when lending native SOL
+    pub lender_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub loan_token_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
@@ -0,0 +1,78 @@
+use crate::{
+    errors::Errors,
+    instructions::cancel_loan_offer::refund_and_close_vault,
+    state::{loan::{LoanInfo, OfferStatus}, config::ProtocolConfig},
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+/// Returns the rent of an offer that can no longer be drawn from once every loan drawn from
+/// it has been settled. The emptied vault of a filled offer is closed along with it.
+pub fn close_loan_offer(ctx: Context<CloseLoanOffer>) -> Result<()> {
+    let loan_info = &ctx.accounts.loan_info;
+    require!(loan_info.status != OfferStatus::Open, Errors::OfferStillOpen);
+    require!(loan_info.outstanding_principal == 0, Errors::OfferHasOutstandingLoans);
+
+    if let Some(vault) = ctx.accounts.vault.as_ref() {
+        refund_and_close_vault(
+            loan_info,
+            vault,
+            ctx.accounts.lender_token_account.as_ref(),
+            &ctx.accounts.loan_token_mint,
+            &ctx.accounts.lender.to_account_info(),
+            &ctx.accounts.token_program,
+        )?;
+    }
+
+    msg!(
+        "Settled loan offer {} closed, rent returned to lender {}",
+        loan_info.key(),
+        ctx.accounts.lender.key()
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CloseLoanOffer<'info> {
+    #[account(
+        mut,
+        close = lender,
+        has_one = lender,
+        seeds = [
+            b"loan_info",
+            lender.key().as_ref(),
+            loan_token_mint.key().as_ref(),
+            &loan_info.offer_id.to_le_bytes()
+        ],
+        bump = loan_info.bump
+    )]
+    /// Stores metadata about the loan info
+    pub loan_info: Account<'info, LoanInfo>,
+
+    #[account(
+        mut,
+        token::mint = loan_token_mint,
+        token::authority = loan_info,
+        seeds = [b"vault", loan_info.key().as_ref()],
+        bump
+    )]
+    /// Vault of a filled offer, already closed for cancelled, expired and request offers
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(seeds = [b"protocol_config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    #[account(
+       mut,
+       token::mint = loan_token_mint,
+       token::authority = lender
+    )]
+    /// Receives anything left in the vault, unused when lending native SOL
+    pub lender_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub loan_token_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
@@ -1,14 +1,16 @@
 use crate::{
     errors::Errors,
-    state::loan::LoanInfo
+    state::{loan::{LoanInfo, OfferStatus}, offer_counter::OfferCounter, config::ProtocolConfig},
+    token_utils::{require_supported_mint, transfer_tokens},
 };
 use anchor_lang::prelude::*;
-use anchor_spl::{
-    token::Transfer,
-    token_interface::{Mint, TokenAccount, TokenInterface},
-    associated_token::AssociatedToken
+use anchor_lang::system_program;
+use anchor_spl::token_interface::{
+    close_account, sync_native, CloseAccount, Mint, SyncNative, TokenAccount, TokenInterface,
 };
+use std::str::FromStr;
 
+#[allow(clippy::too_many_arguments)]
 pub fn create_loan(
     ctx: Context<CreateLoan>,
     loan_amount: u64,
@@ -16,18 +18,52 @@ pub fn create_loan(
     interest_rate_bps: u16,
     duration_seconds: u64,
     min_score: u64,
+    expires_at: i64,
+    min_ticket: u64,
+    max_ticket: u64,
+    allow_partial_release: bool,
+    max_ltv_bps: u16,
+    liquidation_threshold_bps: u16,
 ) -> Result<()> {
-    require!(loan_amount > 0, Errors::InvalidLoanAmount);
-    require!(collateral_amount > 0, Errors::InvalidCollateralAmount);
-    require!(interest_rate_bps > 0, Errors::InvalidInterestRate);
-    require!(duration_seconds > 0, Errors::InvalidDuration);
-    require!(min_score <= 1000, Errors::InvalidScore);
+    // A zero max LTV leaves the offer unpriced, otherwise loans must start below the threshold
+    require!(
+        (max_ltv_bps == 0 && liquidation_threshold_bps == 0)
+            || (max_ltv_bps > 0 && max_ltv_bps < liquidation_threshold_bps && liquidation_threshold_bps <= 10_000),
+        Errors::InvalidLtvSettings
+    );
+    validate_loan_terms(
+        loan_amount,
+        collateral_amount,
+        interest_rate_bps,
+        duration_seconds,
+        min_score,
+        min_ticket,
+        max_ticket,
+    )?;
+    let config = &ctx.accounts.config;
+    config.require_offers_open(&ctx.accounts.loan_token_mint.key(), &ctx.accounts.accepted_token_mint.key())?;
+    config.require_mint_allowed(&ctx.accounts.loan_token_mint.key())?;
+    config.require_mint_allowed(&ctx.accounts.accepted_token_mint.key())?;
+    config.require_within_limits(loan_amount, duration_seconds)?;
+    require_supported_mint(&ctx.accounts.loan_token_mint)?;
+    require_supported_mint(&ctx.accounts.accepted_token_mint)?;
+
+    let clock = Clock::get()?;
+    require!(expires_at > clock.unix_timestamp, Errors::InvalidOfferExpiry);
 
     let loan_info= &mut ctx.accounts.loan_info;
     let lender = &ctx.accounts.lender;
 
+    // Hand out the next offer id for this lender
+    let offer_counter = &mut ctx.accounts.offer_counter;
+    offer_counter.lender = lender.key();
+    offer_counter.bump = ctx.bumps.offer_counter;
+    let offer_id = offer_counter.next_offer_id;
+    offer_counter.next_offer_id = offer_id.checked_add(1).ok_or(Errors::MathOverflow)?;
+
     //Init loan offer account
     loan_info.lender = lender.key();
+    loan_info.offer_id = offer_id;
     loan_info.loan_token_mint = ctx.accounts.loan_token_mint.key();
     loan_info.collateral_token_mint = ctx.accounts.accepted_token_mint.key();
     loan_info.loan_amount= loan_amount;
@@ -36,36 +72,162 @@ pub fn create_loan(
     loan_info.duration_seconds = duration_seconds;
     loan_info.min_score = min_score;
     loan_info.vault = ctx.accounts.vault.key();
-    loan_info.is_active = false;
+    loan_info.created_at = clock.unix_timestamp;
+    loan_info.expires_at = expires_at;
+    loan_info.min_ticket = min_ticket;
+    loan_info.max_ticket = max_ticket;
+    loan_info.remaining_amount = loan_amount;
+    loan_info.outstanding_principal = 0;
+    loan_info.allow_partial_release = allow_partial_release;
+    loan_info.max_ltv_bps = max_ltv_bps;
+    loan_info.liquidation_threshold_bps = liquidation_threshold_bps;
+    loan_info.status = OfferStatus::Open;
     loan_info.bump = ctx.bumps.loan_info;
 
     //Transfer loan to vault
-    let cpi_accounts = Transfer {
-        from: ctx.accounts.lender_token_account.to_account_info(),
-        to: ctx.accounts.vault.to_account_info(),
-        authority: lender.to_account_info(),
+    let funded = if ctx.accounts.loan_token_mint.key() == Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap() {
+        // Native SOL gets wrapped into the WSOL vault
+        wrap_sol(
+            &lender.to_account_info(),
+            &ctx.accounts.vault.to_account_info(),
+            &ctx.accounts.system_program,
+            &ctx.accounts.token_program,
+            loan_amount,
+        )?;
+        loan_amount
+    } else {
+        let lender_token_account = ctx.accounts.lender_token_account
+            .as_ref()
+            .ok_or(Errors::MissingLenderTokenAccount)?;
+        transfer_tokens(
+            &lender_token_account.to_account_info(),
+            &ctx.accounts.vault.to_account_info(),
+            &ctx.accounts.loan_token_mint,
+            &lender.to_account_info(),
+            &ctx.accounts.token_program,
+            loan_amount,
+            &[],
+        )?
     };
-    let cpi_program = ctx.accounts.token_program.to_account_info();
-    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-    anchor_spl::token::transfer(cpi_ctx, loan_amount)?;
+    ctx.accounts.loan_info.set_funded_amount(funded)?;
 
     msg!(
-        "Loan offer created with amount: {} and interest rate: {} bps",
-        loan_amount,
-        interest_rate_bps
+        "Loan offer {} created with amount: {} and interest rate: {} bps, max LTV: {} bps",
+        offer_id,
+        funded,
+        interest_rate_bps,
+        max_ltv_bps
     );
 
     Ok(())
 }
 
+/// Checks shared by every instruction that sets the terms of a loan offer
+pub(crate) fn validate_loan_terms(
+    loan_amount: u64,
+    collateral_amount: u64,
+    interest_rate_bps: u16,
+    duration_seconds: u64,
+    min_score: u64,
+    min_ticket: u64,
+    max_ticket: u64,
+) -> Result<()> {
+    require!(loan_amount > 0, Errors::InvalidLoanAmount);
+    require!(collateral_amount > 0, Errors::InvalidCollateralAmount);
+    require!(interest_rate_bps > 0, Errors::InvalidInterestRate);
+    require!(duration_seconds > 0, Errors::InvalidDuration);
+    require!(min_score <= 1000, Errors::InvalidScore);
+    require!(
+        min_ticket > 0 && min_ticket <= max_ticket && max_ticket <= loan_amount,
+        Errors::InvalidTicketSize
+    );
+    Ok(())
+}
+
+/// Sends native SOL from `from` into the WSOL token account `to` and syncs its balance
+pub(crate) fn wrap_sol<'info>(
+    from: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    token_program: &Interface<'info, TokenInterface>,
+    amount: u64,
+) -> Result<()> {
+    let cpi_accounts = system_program::Transfer {
+        from: from.clone(),
+        to: to.clone(),
+    };
+    let cpi_ctx = CpiContext::new(system_program.to_account_info(), cpi_accounts);
+    system_program::transfer(cpi_ctx, amount)?;
+
+    let cpi_accounts = SyncNative { account: to.clone() };
+    let cpi_ctx = CpiContext::new(token_program.to_account_info(), cpi_accounts);
+    sync_native(cpi_ctx)
+}
+
+/// Pays `amount` out of a WSOL vault as native SOL. The WSOL is moved into a temporary
+/// account owned by the offer, which is closed straight away so `recipient` gets the
+/// lamports along with the temporary account's rent.
+pub(crate) fn unwrap_sol<'info>(
+    loan_info: &Account<'info, LoanInfo>,
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    unwrap_account: &InterfaceAccount<'info, TokenAccount>,
+    native_mint: &InterfaceAccount<'info, Mint>,
+    recipient: &AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+    amount: u64,
+) -> Result<()> {
+    let offer_id = loan_info.offer_id.to_le_bytes();
+    let seeds = &[
+        b"loan_info",
+        loan_info.lender.as_ref(),
+        loan_info.loan_token_mint.as_ref(),
+        offer_id.as_ref(),
+        &[loan_info.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    transfer_tokens(
+        &vault.to_account_info(),
+        &unwrap_account.to_account_info(),
+        native_mint,
+        &loan_info.to_account_info(),
+        token_program,
+        amount,
+        signer,
+    )?;
+
+    let cpi_accounts = CloseAccount {
+        account: unwrap_account.to_account_info(),
+        destination: recipient.clone(),
+        authority: loan_info.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer);
+    close_account(cpi_ctx)
+}
+
 #[derive(Accounts)]
 #[instruction(amount: u64, interest_rate_bps: u16, duration_slots: u64, min_score: u64)]
 pub struct CreateLoan<'info> {
+    #[account(
+        init_if_needed,
+        payer = lender,
+        space = 8 + 32 + 8 + 1,
+        seeds = [b"offer_counter", lender.key().as_ref()],
+        bump
+    )]
+    /// Hands out offer ids so a lender can have many offers per mint
+    pub offer_counter: Account<'info, OfferCounter>,
+
     #[account(
         init,
         payer = lender,
-        space = 8 + 32 + 32 + 32 + 8 + 8 + 2 + 8 + 8 + 32 + 1 + 1,
-        seeds = [b"loan_info", lender.key().as_ref(), loan_token_mint.key().as_ref()],
+        space = 8 + 32 + 8 + 32 + 32 + 8 + 8 + 2 + 8 + 8 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 2 + 2 + 1 + 1,
+        seeds = [
+            b"loan_info",
+            lender.key().as_ref(),
+            loan_token_mint.key().as_ref(),
+            &offer_counter.next_offer_id.to_le_bytes()
+        ],
         bump
     )]
     /// Stores metadata about the loan info
@@ -82,6 +244,9 @@ pub struct CreateLoan<'info> {
     /// Holds the tokens sent out for a loan prior to a second party borrowing
     pub vault: InterfaceAccount<'info, TokenAccount>,
 
+    #[account(seeds = [b"protocol_config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+
     #[account(mut)]
     pub lender: Signer<'info>,
 
@@ -90,7 +255,8 @@ pub struct CreateLoan<'info> {
        token::mint = loan_token_mint,
        token::authority = lender
     )]
-    pub lender_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// Funds the offer, unused when lending native SOL
+    pub lender_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
     pub loan_token_mint: InterfaceAccount<'info, Mint>,
     pub accepted_token_mint: InterfaceAccount<'info, Mint>,
     pub token_program: Interface<'info, TokenInterface>,
@@ -0,0 +1,128 @@
+use crate::{
+    errors::Errors,
+    state::{loan_request::LoanRequest, config::ProtocolConfig},
+    token_utils::{require_supported_mint, transfer_tokens},
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use std::str::FromStr;
+
+pub fn create_loan_request(
+    ctx: Context<CreateLoanRequest>,
+    request_id: u64,
+    loan_amount: u64,
+    collateral_amount: u64,
+    max_interest_rate_bps: u16,
+    duration_seconds: u64,
+) -> Result<()> {
+    require!(loan_amount > 0, Errors::InvalidLoanAmount);
+    require!(collateral_amount > 0, Errors::InvalidCollateralAmount);
+    require!(max_interest_rate_bps > 0, Errors::InvalidInterestRate);
+    require!(duration_seconds > 0, Errors::InvalidDuration);
+    let config = &ctx.accounts.config;
+    config.require_borrows_open(&ctx.accounts.loan_token_mint.key(), &ctx.accounts.collateral_token_mint.key())?;
+    config.require_mint_allowed(&ctx.accounts.loan_token_mint.key())?;
+    config.require_mint_allowed(&ctx.accounts.collateral_token_mint.key())?;
+    config.require_within_limits(loan_amount, duration_seconds)?;
+    require_supported_mint(&ctx.accounts.loan_token_mint)?;
+    require_supported_mint(&ctx.accounts.collateral_token_mint)?;
+
+    let loan_request = &mut ctx.accounts.loan_request;
+    loan_request.borrower = ctx.accounts.borrower.key();
+    loan_request.request_id = request_id;
+    loan_request.loan_token_mint = ctx.accounts.loan_token_mint.key();
+    loan_request.collateral_token_mint = ctx.accounts.collateral_token_mint.key();
+    loan_request.loan_amount = loan_amount;
+    loan_request.collateral_amount = collateral_amount;
+    loan_request.max_interest_rate_bps = max_interest_rate_bps;
+    loan_request.duration_seconds = duration_seconds;
+    loan_request.created_at = Clock::get()?.unix_timestamp;
+    loan_request.bump = ctx.bumps.loan_request;
+
+    // Escrow the collateral until a lender fills the request
+    if ctx.accounts.collateral_token_mint.key() == Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap() {
+        let ix = solana_program::system_instruction::transfer(
+            &ctx.accounts.borrower.key(),
+            &ctx.accounts.loan_request.key(),
+            collateral_amount,
+        );
+        solana_program::program::invoke(
+            &ix,
+            &[
+                ctx.accounts.borrower.to_account_info(),
+                ctx.accounts.loan_request.to_account_info(),
+            ],
+        )?;
+    } else {
+        let borrower_token_account = ctx.accounts.borrower_collateral_token_account
+            .as_ref()
+            .ok_or(Errors::MissingBorrowerTokenAccount)?;
+        let collateral_escrow = ctx.accounts.collateral_escrow
+            .as_ref()
+            .ok_or(Errors::MissingCollateralVaultTokenAccount)?;
+        // The request offers whatever collateral arrives after the transfer fee
+        let escrowed = transfer_tokens(
+            &borrower_token_account.to_account_info(),
+            &collateral_escrow.to_account_info(),
+            &ctx.accounts.collateral_token_mint,
+            &ctx.accounts.borrower.to_account_info(),
+            &ctx.accounts.token_program,
+            collateral_amount,
+            &[],
+        )?;
+        require!(escrowed > 0, Errors::InvalidCollateralAmount);
+        ctx.accounts.loan_request.collateral_amount = escrowed;
+    }
+
+    msg!(
+        "Loan request {} created by {} for {} at up to {} bps",
+        request_id,
+        ctx.accounts.borrower.key(),
+        loan_amount,
+        max_interest_rate_bps
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(request_id: u64)]
+pub struct CreateLoanRequest<'info> {
+    #[account(
+        init,
+        payer = borrower,
+        space = 8 + 32 + 8 + 32 + 32 + 8 + 8 + 2 + 8 + 8 + 1,
+        seeds = [b"loan_request", borrower.key().as_ref(), &request_id.to_le_bytes()],
+        bump
+    )]
+    /// Stores the terms the borrower is asking for
+    pub loan_request: Account<'info, LoanRequest>,
+
+    #[account(
+        init,
+        payer = borrower,
+        token::mint = collateral_token_mint,
+        token::authority = loan_request,
+        seeds = [b"request_escrow", loan_request.key().as_ref()],
+        bump
+    )]
+    /// Holds SPL collateral until the request is filled or cancelled, unused for SOL collateral
+    pub collateral_escrow: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(seeds = [b"protocol_config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    #[account(
+       mut,
+       token::mint = collateral_token_mint,
+       token::authority = borrower
+    )]
+    pub borrower_collateral_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub loan_token_mint: InterfaceAccount<'info, Mint>,
+    pub collateral_token_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,332 @@
+use crate::{
+    errors::Errors,
+    state::{
+        borrower_profile::BorrowerProfile,
+        collateral::CollateralVault,
+        config::ProtocolConfig,
+        loan::{LoanInfo, LoanStatus, OfferStatus, OpenLoan},
+        loan_request::LoanRequest,
+        offer_counter::OfferCounter,
+    },
+    token_utils::{amount_with_fee, transfer_tokens},
+};
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{close_account, CloseAccount, Mint, TokenAccount, TokenInterface},
+};
+use std::str::FromStr;
+
+pub fn fill_loan_request(ctx: Context<FillLoanRequest>, interest_rate_bps: u16) -> Result<()> {
+    // Filling a request is both a new offer and a new borrow. The request may predate
+    // the current allow list and limits, so they are checked again for the lender.
+    let config = &ctx.accounts.config;
+    let loan_token_mint = ctx.accounts.loan_request.loan_token_mint;
+    let collateral_token_mint = ctx.accounts.loan_request.collateral_token_mint;
+    config.require_offers_open(&loan_token_mint, &collateral_token_mint)?;
+    config.require_borrows_open(&loan_token_mint, &collateral_token_mint)?;
+    config.require_mint_allowed(&loan_token_mint)?;
+    config.require_mint_allowed(&collateral_token_mint)?;
+    config.require_within_limits(
+        ctx.accounts.loan_request.loan_amount,
+        ctx.accounts.loan_request.duration_seconds,
+    )?;
+    require!(interest_rate_bps > 0, Errors::InvalidInterestRate);
+    require!(
+        interest_rate_bps <= ctx.accounts.loan_request.max_interest_rate_bps,
+        Errors::InterestRateAboveRequestMax
+    );
+
+    let clock = Clock::get()?;
+    let loan_amount = ctx.accounts.loan_request.loan_amount;
+    let collateral_amount = ctx.accounts.loan_request.collateral_amount;
+    let duration_seconds = ctx.accounts.loan_request.duration_seconds;
+
+    // Hand out the next offer id for this lender
+    let offer_counter = &mut ctx.accounts.offer_counter;
+    offer_counter.lender = ctx.accounts.lender.key();
+    offer_counter.bump = ctx.bumps.offer_counter;
+    let offer_id = offer_counter.next_offer_id;
+    offer_counter.next_offer_id = offer_id.checked_add(1).ok_or(Errors::MathOverflow)?;
+
+    // The filled request is recorded as an offer that was taken in full straight away
+    let loan_info = &mut ctx.accounts.loan_info;
+    loan_info.lender = ctx.accounts.lender.key();
+    loan_info.offer_id = offer_id;
+    loan_info.loan_token_mint = ctx.accounts.loan_token_mint.key();
+    loan_info.collateral_token_mint = ctx.accounts.collateral_token_mint.key();
+    loan_info.loan_amount = loan_amount;
+    loan_info.collateral_amount = collateral_amount;
+    loan_info.interest_rate_bps = interest_rate_bps;
+    loan_info.duration_seconds = duration_seconds;
+    loan_info.min_score = 0;
+    // Principal goes from the lender to the borrower directly, no vault is needed
+    loan_info.vault = Pubkey::default();
+    loan_info.created_at = clock.unix_timestamp;
+    loan_info.expires_at = clock.unix_timestamp;
+    loan_info.min_ticket = loan_amount;
+    loan_info.max_ticket = loan_amount;
+    loan_info.remaining_amount = 0;
+    loan_info.outstanding_principal = loan_amount;
+    loan_info.allow_partial_release = false;
+    // Requests are a fixed collateral amount agreed up front, they are not priced
+    loan_info.max_ltv_bps = 0;
+    loan_info.liquidation_threshold_bps = 0;
+    loan_info.status = OfferStatus::Filled;
+    loan_info.bump = ctx.bumps.loan_info;
+
+    // Open the loan
+    let open_loan = &mut ctx.accounts.open_loan;
+    open_loan.loan_info = ctx.accounts.loan_info.key();
+    open_loan.borrower = ctx.accounts.borrower.key();
+    open_loan.principal = loan_amount;
+    open_loan.outstanding_principal = loan_amount;
+    open_loan.start_time = clock.unix_timestamp;
+    open_loan.last_accrual_time = clock.unix_timestamp;
+    open_loan.repay_by_time = clock.unix_timestamp
+        .checked_add(i64::try_from(duration_seconds).map_err(|_| Errors::MathOverflow)?)
+        .ok_or(Errors::MathOverflow)?;
+    open_loan.status = LoanStatus::Active;
+    open_loan.bump = ctx.bumps.open_loan;
+
+    let borrower_profile = &mut ctx.accounts.borrower_profile;
+    borrower_profile.touch(ctx.accounts.borrower.key(), ctx.bumps.borrower_profile);
+    borrower_profile.record_loan_taken(loan_amount)?;
+
+    // Move the escrowed collateral into the collateral vault
+    let borrower = ctx.accounts.borrower.key();
+    let request_id = ctx.accounts.loan_request.request_id.to_le_bytes();
+    let seeds = &[
+        b"loan_request",
+        borrower.as_ref(),
+        request_id.as_ref(),
+        &[ctx.accounts.loan_request.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    let deposited = if ctx.accounts.collateral_token_mint.key() == Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap() {
+        // The escrow reserve comes out of the request rent, which would go to the borrower anyway
+        let sol_escrow = ctx.accounts.sol_escrow.as_ref().ok_or(Errors::MissingSolEscrow)?;
+        let amount = collateral_amount
+            .checked_add(Rent::get()?.minimum_balance(0))
+            .ok_or(Errors::MathOverflow)?;
+        let loan_request = ctx.accounts.loan_request.to_account_info();
+        let sol_escrow = sol_escrow.to_account_info();
+        **loan_request.try_borrow_mut_lamports()? = loan_request
+            .lamports()
+            .checked_sub(amount)
+            .ok_or(Errors::MathOverflow)?;
+        **sol_escrow.try_borrow_mut_lamports()? = sol_escrow
+            .lamports()
+            .checked_add(amount)
+            .ok_or(Errors::MathOverflow)?;
+        collateral_amount
+    } else {
+        let collateral_escrow = ctx.accounts.collateral_escrow
+            .as_ref()
+            .ok_or(Errors::MissingCollateralVaultTokenAccount)?;
+        let collateral_token_account = ctx.accounts.collateral_token_account
+            .as_ref()
+            .ok_or(Errors::MissingCollateralVaultTokenAccount)?;
+        let deposited = transfer_tokens(
+            &collateral_escrow.to_account_info(),
+            &collateral_token_account.to_account_info(),
+            &ctx.accounts.collateral_token_mint,
+            &ctx.accounts.loan_request.to_account_info(),
+            &ctx.accounts.token_program,
+            collateral_escrow.amount,
+            signer,
+        )?;
+
+        let cpi_accounts = CloseAccount {
+            account: collateral_escrow.to_account_info(),
+            destination: ctx.accounts.borrower.to_account_info(),
+            authority: ctx.accounts.loan_request.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        close_account(cpi_ctx)?;
+        deposited
+    };
+
+    // Create the collateral vault
+    let collateral_vault = &mut ctx.accounts.collateral_vault;
+    collateral_vault.borrower = borrower;
+    collateral_vault.token_mint = ctx.accounts.collateral_token_mint.key();
+    collateral_vault.loan_info = ctx.accounts.loan_info.key();
+    collateral_vault.amount = deposited;
+    collateral_vault.bump = ctx.bumps.collateral_vault;
+
+    // Fund the borrower, native SOL goes across as is
+    if ctx.accounts.loan_token_mint.key() == Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap() {
+        let cpi_accounts = system_program::Transfer {
+            from: ctx.accounts.lender.to_account_info(),
+            to: ctx.accounts.borrower.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+        system_program::transfer(cpi_ctx, loan_amount)?;
+    } else {
+        let lender_token_account = ctx.accounts.lender_token_account
+            .as_ref()
+            .ok_or(Errors::MissingLenderTokenAccount)?;
+        let borrower_token_account = ctx.accounts.borrower_token_account
+            .as_ref()
+            .ok_or(Errors::MissingBorrowerTokenAccount)?;
+        // The lender covers any transfer fee, so the borrower gets the principal they owe
+        let received = transfer_tokens(
+            &lender_token_account.to_account_info(),
+            &borrower_token_account.to_account_info(),
+            &ctx.accounts.loan_token_mint,
+            &ctx.accounts.lender.to_account_info(),
+            &ctx.accounts.token_program,
+            amount_with_fee(&ctx.accounts.loan_token_mint, loan_amount)?,
+            &[],
+        )?;
+        require!(received >= loan_amount, Errors::InvalidLoanAmount);
+    }
+
+    msg!(
+        "Loan request {} filled: Lender {} lent {} to borrower {} at {} bps",
+        ctx.accounts.loan_request.key(),
+        ctx.accounts.lender.key(),
+        loan_amount,
+        borrower,
+        interest_rate_bps
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct FillLoanRequest<'info> {
+    #[account(
+        mut,
+        close = borrower,
+        has_one = borrower,
+        has_one = loan_token_mint,
+        has_one = collateral_token_mint,
+        seeds = [b"loan_request", borrower.key().as_ref(), &loan_request.request_id.to_le_bytes()],
+        bump = loan_request.bump
+    )]
+    /// Stores the terms the borrower is asking for
+    pub loan_request: Box<Account<'info, LoanRequest>>,
+
+    #[account(
+        mut,
+        token::mint = collateral_token_mint,
+        token::authority = loan_request,
+        seeds = [b"request_escrow", loan_request.key().as_ref()],
+        bump
+    )]
+    /// Holds SPL collateral until the request is filled, unused for SOL collateral
+    pub collateral_escrow: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        init_if_needed,
+        payer = lender,
+        space = 8 + 32 + 8 + 1,
+        seeds = [b"offer_counter", lender.key().as_ref()],
+        bump
+    )]
+    /// Hands out offer ids so a lender can have many offers per mint
+    pub offer_counter: Box<Account<'info, OfferCounter>>,
+
+    #[account(
+        init,
+        payer = lender,
+        space = 8 + 32 + 8 + 32 + 32 + 8 + 8 + 2 + 8 + 8 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 2 + 2 + 1 + 1,
+        seeds = [
+            b"loan_info",
+            lender.key().as_ref(),
+            loan_token_mint.key().as_ref(),
+            &offer_counter.next_offer_id.to_le_bytes()
+        ],
+        bump
+    )]
+    /// Stores metadata about the loan info
+    pub loan_info: Box<Account<'info, LoanInfo>>,
+
+    #[account(
+        init,
+        payer = lender,
+        space = 8 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 1 + 1,
+        seeds = [b"open_loan", loan_info.key().as_ref(), borrower.key().as_ref()],
+        bump
+    )]
+    /// State of the loan taken by a borrower
+    pub open_loan: Box<Account<'info, OpenLoan>>,
+
+    #[account(
+        init,
+        payer = lender,
+        space = 8 + 32 + 32 + 32 + 8 + 1,
+        seeds = [b"collateral_vault", loan_info.key().as_ref(), borrower.key().as_ref()],
+        bump,
+    )]
+    /// Stores the collateral token and metadata
+    pub collateral_vault: Box<Account<'info, CollateralVault>>,
+
+    #[account(
+        init_if_needed,
+        payer = lender,
+        space = 8 + 32 + 8 + 8 + 8 + 8 + 8 + 1,
+        seeds = [b"borrower_profile", borrower.key().as_ref()],
+        bump
+    )]
+    /// On-chain history of the borrower, created on first use
+    pub borrower_profile: Box<Account<'info, BorrowerProfile>>,
+
+    #[account(
+        init,
+        payer = lender,
+        token::mint = collateral_token_mint,
+        token::authority = collateral_vault,
+        seeds = [b"collateral_token_account", collateral_vault.key().as_ref()],
+        bump
+    )]
+    /// Holds SPL collateral for the loan, unused for SOL collateral
+    pub collateral_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds = [b"sol_escrow", collateral_vault.key().as_ref()],
+        bump
+    )]
+    /// System owned PDA holding SOL collateral, unused for SPL collateral
+    pub sol_escrow: Option<SystemAccount<'info>>,
+
+    #[account(seeds = [b"protocol_config"], bump = config.bump)]
+    pub config: Box<Account<'info, ProtocolConfig>>,
+
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    #[account(
+       mut,
+       token::mint = loan_token_mint,
+       token::authority = lender
+    )]
+    /// Funds the loan, unused when lending native SOL
+    pub lender_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(mut)]
+    /// CHECK: Receives the loan and the request rent, checked against loan_request.borrower
+    pub borrower: AccountInfo<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = lender,
+        associated_token::mint = loan_token_mint,
+        associated_token::authority = borrower,
+        associated_token::token_program = token_program,
+    )]
+    /// Receives the loan, unused when lending native SOL
+    pub borrower_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub loan_token_mint: Box<InterfaceAccount<'info, Mint>>,
+    pub collateral_token_mint: Box<InterfaceAccount<'info, Mint>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
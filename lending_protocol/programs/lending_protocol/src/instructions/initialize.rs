@@ -0,0 +1,78 @@
+use crate::errors::Errors;
+use crate::state::{config::{PauseWindow, ProtocolConfig}, treasury::Treasury};
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::bpf_loader_upgradeable;
+
+/// Sets up the protocol config with the program's upgrade authority as the first admin, so
+/// nobody can take over a fresh deployment by initializing it first
+pub fn initialize(ctx: Context<Initialize>, score_authority: Pubkey) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    config.admin = ctx.accounts.admin.key();
+    config.pending_admin = Pubkey::default();
+    config.score_authority = score_authority;
+    config.protocol_fee_bps = 0;
+    config.pause_new_offers = false;
+    config.pause_new_borrows = false;
+    config.mint_pauses = Vec::new();
+    config.allowed_mints = Vec::new();
+    config.max_loan_amount = 0;
+    config.max_duration_seconds = 0;
+    config.borrow_pause = PauseWindow::default();
+    config.grace_period_seconds = 0;
+    config.max_price_age_seconds = ProtocolConfig::DEFAULT_MAX_PRICE_AGE_SECONDS;
+    config.max_confidence_bps = ProtocolConfig::DEFAULT_MAX_CONFIDENCE_BPS;
+    config.close_factor_bps = ProtocolConfig::DEFAULT_CLOSE_FACTOR_BPS;
+    config.liquidation_bonus_bps = ProtocolConfig::DEFAULT_LIQUIDATION_BONUS_BPS;
+    config.auction_duration_seconds = ProtocolConfig::DEFAULT_AUCTION_DURATION_SECONDS;
+    config.auction_start_premium_bps = ProtocolConfig::DEFAULT_AUCTION_START_PREMIUM_BPS;
+    config.auction_reserve_bps = ProtocolConfig::DEFAULT_AUCTION_RESERVE_BPS;
+    config.bump = ctx.bumps.config;
+    ctx.accounts.treasury.bump = ctx.bumps.treasury;
+
+    msg!(
+        "Protocol config initialized with admin {} and score authority {}",
+        config.admin,
+        config.score_authority
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + 32 + 32 + 32 + 2 + 1 + 1
+            + 4 + (32 + 8 + 8) * ProtocolConfig::MAX_PAUSED_MINTS
+            + 4 + 32 * ProtocolConfig::MAX_ALLOWED_MINTS
+            + 8 + 8 + 8 + 8 + 8 + 8 + 2 + 2 + 2 + 8 + 2 + 2 + 1,
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    /// Protocol wide settings, one per deployment
+    pub config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + 1,
+        seeds = [b"treasury"],
+        bump
+    )]
+    /// Holds SOL fees and owns the per mint treasury token accounts
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(
+        seeds = [crate::ID.as_ref()],
+        bump,
+        seeds::program = bpf_loader_upgradeable::ID,
+        constraint = program_data.upgrade_authority_address == Some(admin.key()) @ Errors::NotUpgradeAuthority
+    )]
+    /// Program data account of this program, holds the upgrade authority
+    pub program_data: Account<'info, ProgramData>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
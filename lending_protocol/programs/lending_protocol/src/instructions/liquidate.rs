@@ -0,0 +1,406 @@
+use crate::{
+    errors::Errors,
+    events::LoanLiquidated,
+    instructions::take_loan::transfer_from_sol_escrow,
+    oracle::{collateral_for_debt, load_feed_price, loan_to_value_bps},
+    state::{
+        borrower_profile::BorrowerProfile,
+        collateral::CollateralVault,
+        config::ProtocolConfig,
+        loan::{LoanInfo, LoanStatus, OpenLoan},
+        price_feed::PriceFeed,
+        treasury::Treasury,
+    },
+    token_utils::{amount_with_fee, transfer_tokens},
+};
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use anchor_spl::token_interface::{close_account, CloseAccount, Mint, TokenAccount, TokenInterface};
+use std::str::FromStr;
+
+/// Lets anyone liquidate a priced loan at or above its offer's liquidation threshold. The
+/// liquidator repays at most the close factor share of the debt, interest first, and
+/// receives collateral worth what they repaid plus the liquidation bonus. Whatever is not
+/// liquidated stays in the open loan and its collateral vault, unless the vault runs dry
+/// first. The loan is then closed and the principal still owed is written off as bad debt.
+/// Overdue loans are not liquidated here, their collateral goes to auction through
+/// `start_auction` instead.
+///
+/// The oracles of the loan mint and then of the collateral mint are passed as remaining
+/// accounts.
+pub fn liquidate(ctx: Context<Liquidate>, max_repay_amount: u64) -> Result<()> {
+    ctx.accounts.open_loan.require_active()?;
+    require!(ctx.accounts.loan_info.has_price_checks(), Errors::LiquidationDisabled);
+
+    let clock = Clock::get()?;
+    let config = &ctx.accounts.config;
+    let interest = ctx.accounts.open_loan
+        .accrued_interest(ctx.accounts.loan_info.interest_rate_bps, clock.unix_timestamp)?;
+    let outstanding_principal = ctx.accounts.open_loan.outstanding_principal;
+    let debt = outstanding_principal
+        .checked_add(interest)
+        .ok_or(Errors::MathOverflow)?;
+    let collateral_amount = ctx.accounts.collateral_vault.amount;
+
+    let (Some(loan_price_feed), Some(collateral_price_feed)) = (
+        ctx.accounts.loan_price_feed.as_ref(),
+        ctx.accounts.collateral_price_feed.as_ref(),
+    ) else {
+        return err!(Errors::MissingPriceFeed);
+    };
+    let (loan_oracles, collateral_oracles) = ctx.remaining_accounts
+        .split_at_checked(loan_price_feed.oracles.len())
+        .ok_or(Errors::MissingPriceFeed)?;
+    let loan_price = load_feed_price(loan_price_feed, loan_oracles, config, clock.unix_timestamp)?;
+    let collateral_price = load_feed_price(collateral_price_feed, collateral_oracles, config, clock.unix_timestamp)?;
+    let loan_decimals = ctx.accounts.loan_token_mint.decimals;
+    let collateral_decimals = ctx.accounts.collateral_token_mint.decimals;
+
+    let ltv_bps = loan_to_value_bps(
+        debt,
+        &loan_price,
+        loan_decimals,
+        collateral_amount,
+        &collateral_price,
+        collateral_decimals,
+    )?;
+    require!(
+        ltv_bps >= ctx.accounts.loan_info.liquidation_threshold_bps as u64,
+        Errors::LoanNotLiquidatable
+    );
+
+    // One liquidation repays at most the close factor share of the debt, interest first
+    let max_repayable = (debt as u128 * config.close_factor_bps as u128).div_ceil(10_000) as u64;
+    let repay_amount = max_repay_amount.min(max_repayable);
+    require!(repay_amount > 0 && repay_amount >= interest, Errors::InsufficientRepayment);
+    let principal_paid = repay_amount - interest;
+    let remaining_principal = outstanding_principal - principal_paid;
+    let is_settled = remaining_principal == 0;
+
+    let seized = collateral_for_debt(
+        repay_amount,
+        &loan_price,
+        loan_decimals,
+        &collateral_price,
+        collateral_decimals,
+        config.liquidation_bonus_bps,
+    )?
+    .min(collateral_amount);
+    // Once the collateral is gone nothing backs the rest of the debt, so the loan is closed
+    // either way and any principal still owed is bad debt
+    let is_closed = is_settled || seized == collateral_amount;
+    let bad_debt = if is_closed { remaining_principal } else { 0 };
+    // Collateral left once the debt is cleared goes back to the borrower
+    let returned = if is_closed { collateral_amount - seized } else { 0 };
+
+    // The protocol keeps its cut of the interest, the lender gets the rest
+    let protocol_fee = ((interest as u128 * config.protocol_fee_bps as u128) / 10_000) as u64;
+    let lender_amount = repay_amount - protocol_fee;
+
+    // The liquidator repays the lender
+    let is_loaned_sol = ctx.accounts.loan_info.loan_token_mint == Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap();
+    if is_loaned_sol {
+        let cpi_accounts = system_program::Transfer {
+            from: ctx.accounts.liquidator.to_account_info(),
+            to: ctx.accounts.lender.clone(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+        system_program::transfer(cpi_ctx, lender_amount)?;
+
+        if protocol_fee > 0 {
+            let cpi_accounts = system_program::Transfer {
+                from: ctx.accounts.liquidator.to_account_info(),
+                to: ctx.accounts.treasury.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+            system_program::transfer(cpi_ctx, protocol_fee)?;
+        }
+    } else {
+        let liquidator_loan_token_account = ctx.accounts.liquidator_loan_token_account
+            .as_ref()
+            .ok_or(Errors::MissingLiquidatorTokenAccount)?;
+        let lender_token_account = ctx.accounts.lender_token_account
+            .as_ref()
+            .ok_or(Errors::MissingLenderTokenAccount)?;
+        let received = transfer_tokens(
+            &liquidator_loan_token_account.to_account_info(),
+            &lender_token_account.to_account_info(),
+            &ctx.accounts.loan_token_mint,
+            &ctx.accounts.liquidator.to_account_info(),
+            &ctx.accounts.token_program,
+            amount_with_fee(&ctx.accounts.loan_token_mint, lender_amount)?,
+            &[],
+        )?;
+        require!(received >= lender_amount, Errors::InsufficientRepayment);
+
+        if protocol_fee > 0 {
+            let treasury_token_account = ctx.accounts.treasury_token_account
+                .as_ref()
+                .ok_or(Errors::MissingTreasuryTokenAccount)?;
+            let received = transfer_tokens(
+                &liquidator_loan_token_account.to_account_info(),
+                &treasury_token_account.to_account_info(),
+                &ctx.accounts.loan_token_mint,
+                &ctx.accounts.liquidator.to_account_info(),
+                &ctx.accounts.token_program,
+                amount_with_fee(&ctx.accounts.loan_token_mint, protocol_fee)?,
+                &[],
+            )?;
+            require!(received >= protocol_fee, Errors::InsufficientRepayment);
+        }
+    }
+
+    // and takes the seized collateral
+    let is_collateral_sol = ctx.accounts.loan_info.collateral_token_mint == Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap();
+    if is_collateral_sol {
+        let sol_escrow = ctx.accounts.sol_escrow.as_ref().ok_or(Errors::MissingSolEscrow)?;
+        let collateral_vault = ctx.accounts.collateral_vault.key();
+        let bump = ctx.bumps.sol_escrow.ok_or(Errors::MissingSolEscrow)?;
+        if seized > 0 {
+            transfer_from_sol_escrow(
+                sol_escrow,
+                &ctx.accounts.liquidator.to_account_info(),
+                &ctx.accounts.system_program,
+                &collateral_vault,
+                bump,
+                seized,
+            )?;
+        }
+        // Once closed the escrow is drained, rent reserve included
+        if is_closed {
+            transfer_from_sol_escrow(
+                sol_escrow,
+                &ctx.accounts.borrower,
+                &ctx.accounts.system_program,
+                &collateral_vault,
+                bump,
+                sol_escrow.lamports(),
+            )?;
+        }
+    } else {
+        let loan_info = ctx.accounts.loan_info.key();
+        let borrower = ctx.accounts.borrower.key();
+        let seeds = &[
+            b"collateral_vault",
+            loan_info.as_ref(),
+            borrower.as_ref(),
+            &[ctx.accounts.collateral_vault.bump],
+        ];
+        let signer = &[&seeds[..]];
+        let collateral_token_account = ctx.accounts.collateral_token_account
+            .as_ref()
+            .ok_or(Errors::MissingCollateralVaultTokenAccount)?;
+        if seized > 0 {
+            let liquidator_collateral_token_account = ctx.accounts.liquidator_collateral_token_account
+                .as_ref()
+                .ok_or(Errors::MissingLiquidatorTokenAccount)?;
+            transfer_tokens(
+                &collateral_token_account.to_account_info(),
+                &liquidator_collateral_token_account.to_account_info(),
+                &ctx.accounts.collateral_token_mint,
+                &ctx.accounts.collateral_vault.to_account_info(),
+                &ctx.accounts.token_program,
+                seized,
+                signer,
+            )?;
+        }
+        if returned > 0 {
+            let borrower_collateral_token_account = ctx.accounts.borrower_collateral_token_account
+                .as_ref()
+                .ok_or(Errors::MissingBorrowerTokenAccount)?;
+            transfer_tokens(
+                &collateral_token_account.to_account_info(),
+                &borrower_collateral_token_account.to_account_info(),
+                &ctx.accounts.collateral_token_mint,
+                &ctx.accounts.collateral_vault.to_account_info(),
+                &ctx.accounts.token_program,
+                returned,
+                signer,
+            )?;
+        }
+
+        if is_closed {
+            let cpi_accounts = CloseAccount {
+                account: collateral_token_account.to_account_info(),
+                destination: ctx.accounts.borrower.to_account_info(),
+                authority: ctx.accounts.collateral_vault.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, signer);
+            close_account(cpi_ctx)?;
+        }
+    }
+
+    // Written off principal is released too, so the lender can still close the offer
+    ctx.accounts.loan_info.outstanding_principal = ctx.accounts.loan_info.outstanding_principal
+        .checked_sub(principal_paid + bad_debt)
+        .ok_or(Errors::MathOverflow)?;
+    let open_loan = &mut ctx.accounts.open_loan;
+    open_loan.outstanding_principal = remaining_principal - bad_debt;
+    open_loan.last_accrual_time = clock.unix_timestamp;
+    if is_closed {
+        open_loan.transition(LoanStatus::Liquidated)?;
+        ctx.accounts.collateral_vault.close(ctx.accounts.borrower.clone())?;
+        // A loan liquidated in full or left with bad debt counts against the borrower like a default
+        let borrower_profile = &mut ctx.accounts.borrower_profile;
+        borrower_profile.touch(ctx.accounts.borrower.key(), ctx.bumps.borrower_profile);
+        borrower_profile.record_default()?;
+    } else {
+        ctx.accounts.collateral_vault.amount = collateral_amount - seized;
+    }
+
+    emit!(LoanLiquidated {
+        open_loan: ctx.accounts.open_loan.key(),
+        liquidator: ctx.accounts.liquidator.key(),
+        debt_repaid: repay_amount,
+        collateral_seized: seized,
+        remaining_principal: remaining_principal - bad_debt,
+        bad_debt,
+        ltv_bps,
+    });
+
+    msg!(
+        "Loan liquidated: Liquidator {} repaid {} (protocol fee: {}) and received {} collateral, {} principal outstanding, {} written off",
+        ctx.accounts.liquidator.key(),
+        repay_amount,
+        protocol_fee,
+        seized,
+        remaining_principal - bad_debt,
+        bad_debt
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Liquidate<'info> {
+    #[account(
+        mut,
+        seeds = [b"open_loan", loan_info.key().as_ref(), borrower.key().as_ref()],
+        bump = open_loan.bump
+    )]
+    pub open_loan: Account<'info, OpenLoan>,
+
+    #[account(
+        mut,
+        has_one = lender,
+        has_one = loan_token_mint,
+        has_one = collateral_token_mint,
+        seeds = [
+            b"loan_info",
+            lender.key().as_ref(),
+            loan_token_mint.key().as_ref(),
+            &loan_info.offer_id.to_le_bytes()
+        ],
+        bump = loan_info.bump
+    )]
+    pub loan_info: Box<Account<'info, LoanInfo>>,
+
+    #[account(
+        mut,
+        seeds = [b"collateral_vault", loan_info.key().as_ref(), borrower.key().as_ref()],
+        bump = collateral_vault.bump
+    )]
+    pub collateral_vault: Account<'info, CollateralVault>,
+
+    #[account(
+        init_if_needed,
+        payer = liquidator,
+        space = 8 + 32 + 8 + 8 + 8 + 8 + 8 + 1,
+        seeds = [b"borrower_profile", borrower.key().as_ref()],
+        bump
+    )]
+    /// On-chain history of the borrower, created on first use
+    pub borrower_profile: Box<Account<'info, BorrowerProfile>>,
+
+    #[account(
+        mut,
+        token::authority = collateral_vault,
+        seeds = [b"collateral_token_account", collateral_vault.key().as_ref()],
+        bump
+    )]
+    /// Holds SPL collateral for the loan, unused for SOL collateral
+    pub collateral_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds = [b"sol_escrow", collateral_vault.key().as_ref()],
+        bump
+    )]
+    /// System owned PDA holding SOL collateral, unused for SPL collateral
+    pub sol_escrow: Option<SystemAccount<'info>>,
+
+    #[account(seeds = [b"price_feed", loan_token_mint.key().as_ref()], bump = loan_price_feed.bump)]
+    /// Oracle registration for the loan mint
+    pub loan_price_feed: Option<Box<Account<'info, PriceFeed>>>,
+
+    #[account(seeds = [b"price_feed", collateral_token_mint.key().as_ref()], bump = collateral_price_feed.bump)]
+    /// Oracle registration for the collateral mint
+    pub collateral_price_feed: Option<Box<Account<'info, PriceFeed>>>,
+
+    #[account(seeds = [b"protocol_config"], bump = config.bump)]
+    pub config: Box<Account<'info, ProtocolConfig>>,
+
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    /// Receives the protocol fee on SOL loans
+    pub treasury: Box<Account<'info, Treasury>>,
+
+    #[account(
+        init_if_needed,
+        payer = liquidator,
+        token::mint = loan_token_mint,
+        token::authority = treasury,
+        seeds = [b"treasury", loan_token_mint.key().as_ref()],
+        bump
+    )]
+    /// Receives the protocol fee on SPL loans, created on first use
+    pub treasury_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        token::mint = loan_token_mint,
+        token::authority = liquidator
+    )]
+    /// Repays the loan, unused when the loan is native SOL
+    pub liquidator_loan_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        token::mint = collateral_token_mint,
+        token::authority = liquidator
+    )]
+    /// Receives the SPL collateral, unused for SOL collateral
+    pub liquidator_collateral_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        token::mint = loan_token_mint,
+        token::authority = lender
+    )]
+    /// Receives the repayment, unused when the loan is native SOL
+    pub lender_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        token::mint = collateral_token_mint,
+        token::authority = borrower
+    )]
+    /// Receives SPL collateral left over once the debt is cleared, unused for SOL collateral
+    pub borrower_collateral_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub loan_token_mint: Box<InterfaceAccount<'info, Mint>>,
+    pub collateral_token_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub liquidator: Signer<'info>,
+
+    #[account(mut)]
+    /// CHECK: Receives native SOL repayments, checked against the offer
+    pub lender: AccountInfo<'info>,
+
+    #[account(mut, constraint = open_loan.borrower == borrower.key())]
+    /// CHECK: Receives leftover collateral and the vault rent, checked against the open loan
+    pub borrower: AccountInfo<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
@@ -1,7 +1,37 @@
+pub mod initialize;
+pub mod update_config;
+pub mod withdraw_treasury;
+pub mod set_price_feed;
 pub mod create_loan;
 pub mod take_loan;
 pub mod pay_loan;
+pub mod liquidate;
+pub mod start_auction;
+pub mod bid;
+pub mod cancel_loan_offer;
+pub mod close_expired_loan_offer;
+pub mod close_loan_offer;
+pub mod update_loan_offer;
+pub mod add_collateral;
+pub mod create_loan_request;
+pub mod fill_loan_request;
+pub mod cancel_loan_request;
 
+pub use initialize::*;
+pub use update_config::*;
+pub use withdraw_treasury::*;
+pub use set_price_feed::*;
 pub use create_loan::*;
 pub use take_loan::*;
-pub use pay_loan::*;
\ No newline at end of file
+pub use pay_loan::*;
+pub use liquidate::*;
+pub use start_auction::*;
+pub use bid::*;
+pub use cancel_loan_offer::*;
+pub use close_expired_loan_offer::*;
+pub use close_loan_offer::*;
+pub use update_loan_offer::*;
+pub use add_collateral::*;
+pub use create_loan_request::*;
+pub use fill_loan_request::*;
+pub use cancel_loan_request::*;
\ No newline at end of file
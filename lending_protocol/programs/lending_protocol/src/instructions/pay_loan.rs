@@ -2,133 +2,207 @@ use anchor_lang::context::{Context, CpiContext};
 use anchor_lang::prelude::*;
 use anchor_lang::{require, Accounts, Key, ToAccountInfo};
 use anchor_spl::associated_token::AssociatedToken;
-use anchor_spl::token::Transfer;
 use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
-use solana_program::program::invoke_signed;
 use crate::errors::Errors;
+use crate::instructions::take_loan::transfer_from_sol_escrow;
+use crate::state::borrower_profile::BorrowerProfile;
 use crate::state::collateral::CollateralVault;
-use crate::state::loan::{LoanInfo, OpenLoan};
+use crate::state::config::ProtocolConfig;
+use crate::state::loan::{LoanInfo, LoanStatus, OpenLoan};
+use crate::state::treasury::Treasury;
+use crate::token_utils::{amount_with_fee, transfer_tokens};
 
-pub fn pay_loan(ctx: Context<PayLoan>) -> anchor_lang::Result<()> {
-    use anchor_lang::solana_program::{program::invoke, system_instruction};
-    use anchor_spl::token::{transfer, Transfer};
+pub fn pay_loan(ctx: Context<PayLoan>, repay_amount: u64) -> anchor_lang::Result<()> {
+    use anchor_lang::system_program;
+    use anchor_spl::token_interface::{close_account, CloseAccount};
 
-    require!(!ctx.accounts.open_loan.is_repaid, Errors::LoanAlreadyRepaid);
+    ctx.accounts.open_loan.require_active()?;
+    require!(repay_amount > 0, Errors::InsufficientRepayment);
 
-    const SECONDS_PER_YEAR: u64 = 31_536_000;
-
-    msg!("11111111");
     let clock = Clock::get()?;
-    let time_elapsed = (clock.unix_timestamp - ctx.accounts.open_loan.start_time).max(0) as u64;
-    let duration_seconds = (ctx.accounts.open_loan.repay_by_time - ctx.accounts.open_loan.start_time) as u64;
-    let effective_time = time_elapsed.min(duration_seconds);
-    msg!("22222");
+    // Loans that matured during a pause can still be repaid, late, within the grace period
+    let repay_by_time = ctx.accounts.config.effective_repay_by(
+        ctx.accounts.open_loan.repay_by_time,
+        &ctx.accounts.loan_info.loan_token_mint,
+        &ctx.accounts.loan_info.collateral_token_mint,
+    );
+    require!(
+        clock.unix_timestamp <= repay_by_time,
+        Errors::LoanRepaymentOverdue
+    );
+    // Interest accrues on the outstanding balance since the last payment
+    let interest = ctx.accounts.open_loan
+        .accrued_interest(ctx.accounts.loan_info.interest_rate_bps, clock.unix_timestamp)?;
+
+    let outstanding_principal = ctx.accounts.open_loan.outstanding_principal;
+    let amount_owed = outstanding_principal.checked_add(interest).ok_or(Errors::MathOverflow)?;
 
-    let principal = ctx.accounts.open_loan.principal as u128;
-    let interest_rate_bps = ctx.accounts.loan_info.interest_rate_bps as u128;
-    let effective_time = effective_time as u128;
-    let denominator = SECONDS_PER_YEAR as u128 * 10_000;
+    // Payments settle accrued interest first, anything above that pays down principal
+    require!(repay_amount >= interest, Errors::InsufficientRepayment);
+    let total_amount = repay_amount.min(amount_owed);
+    let principal_paid = total_amount - interest;
+    let remaining_principal = outstanding_principal - principal_paid;
+    let is_settled = remaining_principal == 0;
 
-    let interest = ((principal * interest_rate_bps * effective_time) / denominator) as u64;
-    let total_amount = ctx.accounts.open_loan.principal.checked_add(interest).ok_or(Errors::MathOverflow)?;
+    // The protocol keeps its cut of the interest, the lender gets the rest
+    let protocol_fee = ((interest as u128 * ctx.accounts.config.protocol_fee_bps as u128) / 10_000) as u64;
+    let lender_amount = total_amount - protocol_fee;
 
-    msg!("33333");
     let is_loaned_sol = ctx.accounts.loaned_token_mint.key().to_string() == "So11111111111111111111111111111111111111112";
 
     if is_loaned_sol {
-        msg!("44444");
-        invoke(
-            &system_instruction::transfer(
-                &ctx.accounts.borrower.key(),
-                &ctx.accounts.lender.key(),
-                total_amount,
-            ),
-            &[
-                ctx.accounts.borrower.to_account_info(),
-                ctx.accounts.lender.clone(),
-                ctx.accounts.system_program.to_account_info(),
-            ],
-        )?;
-    } else {
-        msg!("55555");
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.borrower_token_account.to_account_info(),
-            to: ctx.accounts.lender_token_account.to_account_info(),
-            authority: ctx.accounts.borrower.to_account_info(),
+        let cpi_accounts = system_program::Transfer {
+            from: ctx.accounts.borrower.to_account_info(),
+            to: ctx.accounts.lender.clone(),
         };
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-        transfer(cpi_ctx, total_amount)?;
+        let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+        system_program::transfer(cpi_ctx, lender_amount)?;
+
+        if protocol_fee > 0 {
+            let cpi_accounts = system_program::Transfer {
+                from: ctx.accounts.borrower.to_account_info(),
+                to: ctx.accounts.treasury.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+            system_program::transfer(cpi_ctx, protocol_fee)?;
+        }
+    } else {
+        let borrower_token_account = ctx.accounts.borrower_token_account
+            .as_ref()
+            .ok_or(Errors::MissingBorrowerTokenAccount)?;
+        let lender_token_account = ctx.accounts.lender_token_account
+            .as_ref()
+            .ok_or(Errors::MissingLenderTokenAccount)?;
+        // The borrower covers any transfer fee, so the lender and treasury get what they are owed
+        let received = transfer_tokens(
+            &borrower_token_account.to_account_info(),
+            &lender_token_account.to_account_info(),
+            &ctx.accounts.loaned_token_mint,
+            &ctx.accounts.borrower.to_account_info(),
+            &ctx.accounts.token_program,
+            amount_with_fee(&ctx.accounts.loaned_token_mint, lender_amount)?,
+            &[],
+        )?;
+        require!(received >= lender_amount, Errors::InsufficientRepayment);
+
+        if protocol_fee > 0 {
+            let treasury_token_account = ctx.accounts.treasury_token_account
+                .as_ref()
+                .ok_or(Errors::MissingTreasuryTokenAccount)?;
+            let received = transfer_tokens(
+                &borrower_token_account.to_account_info(),
+                &treasury_token_account.to_account_info(),
+                &ctx.accounts.loaned_token_mint,
+                &ctx.accounts.borrower.to_account_info(),
+                &ctx.accounts.token_program,
+                amount_with_fee(&ctx.accounts.loaned_token_mint, protocol_fee)?,
+                &[],
+            )?;
+            require!(received >= protocol_fee, Errors::InsufficientRepayment);
+        }
     }
 
-    ctx.accounts.open_loan.is_repaid = true;
-    ctx.accounts.loan_info.is_active = false;
+    let open_loan = &mut ctx.accounts.open_loan;
+    open_loan.outstanding_principal = remaining_principal;
+    open_loan.last_accrual_time = clock.unix_timestamp;
+    if is_settled {
+        open_loan.transition(LoanStatus::Repaid)?;
+        // Paying within a pause grace extension still counts as on time
+        let on_time = clock.unix_timestamp <= repay_by_time;
+        let borrower_profile = &mut ctx.accounts.borrower_profile;
+        borrower_profile.touch(ctx.accounts.borrower.key(), ctx.bumps.borrower_profile);
+        borrower_profile.record_repayment(on_time)?;
+    }
+    ctx.accounts.loan_info.outstanding_principal = ctx.accounts.loan_info.outstanding_principal
+        .checked_sub(principal_paid)
+        .ok_or(Errors::MathOverflow)?;
 
-    msg!("6666");
     let is_collateral_sol = ctx.accounts.collateral_token_mint.key().to_string() == "So11111111111111111111111111111111111111112";
-    let amount_to_return = ctx.accounts.collateral_vault.amount;
+    // Collateral comes back in full once settled, or pro rata to principal if the lender allows it
+    let amount_to_return = if is_settled {
+        ctx.accounts.collateral_vault.amount
+    } else if ctx.accounts.loan_info.allow_partial_release {
+        ((ctx.accounts.collateral_vault.amount as u128 * principal_paid as u128)
+            / outstanding_principal as u128) as u64
+    } else {
+        0
+    };
 
-    if amount_to_return > 0 {
-        msg!("7777");
-        let loan_info = ctx.accounts.loan_info.key();
-        let borrower = ctx.accounts.borrower.key();
-        let seeds = &[
-            b"collateral_vault",
-            loan_info.as_ref(),
-            borrower.as_ref(),
-            &[ctx.bumps.collateral_vault],
-        ];
-        let signer = &[&seeds[..]];
+    let loan_info = ctx.accounts.loan_info.key();
+    let borrower = ctx.accounts.borrower.key();
+    let seeds = &[
+        b"collateral_vault",
+        loan_info.as_ref(),
+        borrower.as_ref(),
+        &[ctx.bumps.collateral_vault],
+    ];
+    let signer = &[&seeds[..]];
 
+    if amount_to_return > 0 {
         if is_collateral_sol {
-            use anchor_lang::solana_program::rent::Rent;
-
-            let collateral_vault = ctx.accounts.collateral_vault.to_account_info();
-            let borrower_account_info = ctx.accounts.borrower.to_account_info();
-
-            let rent = Rent::get()?;
-            let rent_exempt_minimum = rent.minimum_balance(collateral_vault.data_len());
-
-            let vault_lamports = **collateral_vault.lamports.borrow();
-            let amount_to_transfer = vault_lamports
-                .checked_sub(rent_exempt_minimum)
-                .ok_or(Errors::MathOverflow)?;
-
-            // Debit collateral vault lamports
-            **collateral_vault.lamports.borrow_mut() = vault_lamports
-                .checked_sub(amount_to_transfer)
-                .ok_or(Errors::MathOverflow)?;
-
-            // Credit borrower lamports
-            **borrower_account_info.lamports.borrow_mut() = borrower_account_info
-                .lamports()
-                .checked_add(amount_to_transfer)
-                .ok_or(Errors::MathOverflow)?;
+            let sol_escrow = ctx.accounts.sol_escrow.as_ref().ok_or(Errors::MissingSolEscrow)?;
+            // Once settled the escrow is drained, rent reserve included
+            let amount_to_transfer = if is_settled { sol_escrow.lamports() } else { amount_to_return };
+            transfer_from_sol_escrow(
+                sol_escrow,
+                &ctx.accounts.borrower.to_account_info(),
+                &ctx.accounts.system_program,
+                &ctx.accounts.collateral_vault.key(),
+                ctx.bumps.sol_escrow.ok_or(Errors::MissingSolEscrow)?,
+                amount_to_transfer,
+            )?;
         } else {
-            msg!("999");
-            let cpi_accounts = Transfer {
-                from: ctx.accounts.collateral_vault_token_account.to_account_info(),
-                to: ctx.accounts.borrower_token_account.to_account_info(),
-                authority: ctx.accounts.collateral_vault.to_account_info(),
-            };
-            msg!("101010101001");
-            let cpi_program = ctx.accounts.token_program.to_account_info();
-            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
-            msg!("210101010101");
-            transfer(cpi_ctx, amount_to_return)?;
-            msg!("33101001010");
+            let collateral_token_account = ctx.accounts.collateral_token_account
+                .as_ref()
+                .ok_or(Errors::MissingCollateralVaultTokenAccount)?;
+            let borrower_collateral_token_account = ctx.accounts.borrower_collateral_token_account
+                .as_ref()
+                .ok_or(Errors::MissingBorrowerTokenAccount)?;
+            transfer_tokens(
+                &collateral_token_account.to_account_info(),
+                &borrower_collateral_token_account.to_account_info(),
+                &ctx.accounts.collateral_token_mint,
+                &ctx.accounts.collateral_vault.to_account_info(),
+                &ctx.accounts.token_program,
+                amount_to_return,
+                signer,
+            )?;
         }
 
+        ctx.accounts.collateral_vault.amount -= amount_to_return;
         msg!("Returned {} collateral to borrower", amount_to_return);
     }
 
+    // The emptied collateral token account goes away once the loan is settled
+    if is_settled && !is_collateral_sol {
+        let collateral_token_account = ctx.accounts.collateral_token_account
+            .as_ref()
+            .ok_or(Errors::MissingCollateralVaultTokenAccount)?;
+        let cpi_accounts = CloseAccount {
+            account: collateral_token_account.to_account_info(),
+            destination: ctx.accounts.borrower.to_account_info(),
+            authority: ctx.accounts.collateral_vault.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        close_account(cpi_ctx)?;
+    }
+
+    // A settled loan has nothing left to track, its rent goes back to the borrower
+    if is_settled {
+        ctx.accounts.collateral_vault.close(ctx.accounts.borrower.to_account_info())?;
+        ctx.accounts.open_loan.close(ctx.accounts.borrower.to_account_info())?;
+    }
+
     msg!(
-        "Loan repaid: Borrower {} paid {} (principal: {}, interest: {}) to lender {}",
+        "Loan payment: Borrower {} paid {} (principal: {}, interest: {}, protocol fee: {}) to lender {}, {} principal outstanding",
         ctx.accounts.borrower.key(),
         total_amount,
-        ctx.accounts.open_loan.principal,
+        principal_paid,
         interest,
-        ctx.accounts.loan_info.lender
+        protocol_fee,
+        ctx.accounts.loan_info.lender,
+        remaining_principal
     );
 
     Ok(())
@@ -145,8 +219,13 @@ pub struct PayLoan<'info> {
 
     #[account(
         mut,
-        seeds = [b"loan_info", lender.key().as_ref(), loaned_token_mint.key().as_ref()],
-        bump
+        seeds = [
+            b"loan_info",
+            lender.key().as_ref(),
+            loaned_token_mint.key().as_ref(),
+            &loan_info.offer_id.to_le_bytes()
+        ],
+        bump = loan_info.bump
     )]
     pub loan_info: Account<'info, LoanInfo>,
 
@@ -157,28 +236,86 @@ pub struct PayLoan<'info> {
     )]
     pub collateral_vault: Account<'info, CollateralVault>,
 
-    #[account(mut)]
-    /// CHECK: Only used if loaned token is SPL
-    pub borrower_token_account: AccountInfo<'info>,
+    #[account(
+        init_if_needed,
+        payer = borrower,
+        space = 8 + 32 + 8 + 8 + 8 + 8 + 8 + 1,
+        seeds = [b"borrower_profile", borrower.key().as_ref()],
+        bump
+    )]
+    /// On-chain history of the borrower, created on first use
+    pub borrower_profile: Account<'info, BorrowerProfile>,
 
-    #[account(mut)]
-    /// CHECK: Only used if loaned token is SPL
-    pub lender_token_account: AccountInfo<'info>,
+    #[account(
+        mut,
+        token::mint = loaned_token_mint,
+        token::authority = borrower
+    )]
+    /// Pays the loan back, unused when the loan is native SOL
+    pub borrower_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
 
-    #[account(mut)]
-    /// CHECK: Only used if collateral is SPL
-    pub collateral_vault_token_account: AccountInfo<'info>,
+    #[account(
+        mut,
+        token::mint = loaned_token_mint,
+        token::authority = lender
+    )]
+    /// Receives the repayment, unused when the loan is native SOL
+    pub lender_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
 
-    /// CHECK: Used to determine if collateral is SOL
-    pub collateral_token_mint: AccountInfo<'info>,
+    #[account(
+        mut,
+        token::authority = borrower,
+        constraint = borrower_collateral_token_account.mint == loan_info.collateral_token_mint @ Errors::InvalidCollateralToken
+    )]
+    /// Receives the released SPL collateral, unused for SOL collateral
+    pub borrower_collateral_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
 
-    /// CHECK: Used to determine if loaned token is SOL
-    pub loaned_token_mint: AccountInfo<'info>,
+    #[account(
+        mut,
+        token::authority = collateral_vault,
+        seeds = [b"collateral_token_account", collateral_vault.key().as_ref()],
+        bump
+    )]
+    /// Holds SPL collateral for the loan, unused for SOL collateral
+    pub collateral_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds = [b"sol_escrow", collateral_vault.key().as_ref()],
+        bump
+    )]
+    /// System owned PDA holding SOL collateral, unused for SPL collateral
+    pub sol_escrow: Option<SystemAccount<'info>>,
+
+    #[account(address = loan_info.collateral_token_mint)]
+    /// Used to determine if collateral is SOL
+    pub collateral_token_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// Used to determine if loaned token is SOL
+    pub loaned_token_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(seeds = [b"protocol_config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    /// Receives the protocol fee on SOL loans
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(
+        init_if_needed,
+        payer = borrower,
+        token::mint = loaned_token_mint,
+        token::authority = treasury,
+        seeds = [b"treasury", loaned_token_mint.key().as_ref()],
+        bump
+    )]
+    /// Receives the protocol fee on SPL loans, created on first use
+    pub treasury_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
 
     #[account(mut)]
     pub borrower: Signer<'info>,
 
-    #[account(constraint = loan_info.lender == lender.key())]
+    #[account(mut, constraint = loan_info.lender == lender.key())]
     /// CHECK: PDA only
     pub lender: AccountInfo<'info>,
 
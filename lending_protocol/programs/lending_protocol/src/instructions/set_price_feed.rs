@@ -0,0 +1,55 @@
+use crate::{
+    errors::Errors,
+    oracle::load_pyth_price,
+    state::{config::ProtocolConfig, price_feed::PriceFeed},
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+
+/// Points `mint` at the oracle accounts its price is read from, passed as remaining
+/// accounts, replacing any earlier ones
+pub fn set_price_feed(ctx: Context<SetPriceFeed>) -> Result<()> {
+    let oracles = ctx.remaining_accounts;
+    require!(
+        !oracles.is_empty() && oracles.len() <= PriceFeed::MAX_ORACLES,
+        Errors::InvalidPriceSourceCount
+    );
+    let mut keys = Vec::with_capacity(oracles.len());
+    for oracle in oracles {
+        // A source listed twice would count twice towards the median
+        require!(!keys.contains(oracle.key), Errors::InvalidPriceSourceCount);
+        // Refuse accounts that are not price accounts at all
+        load_pyth_price(oracle)?;
+        keys.push(oracle.key());
+    }
+
+    let price_feed = &mut ctx.accounts.price_feed;
+    price_feed.mint = ctx.accounts.mint.key();
+    price_feed.oracles = keys;
+    price_feed.bump = ctx.bumps.price_feed;
+
+    msg!("Price feed for mint {} set to {} oracles", price_feed.mint, price_feed.oracles.len());
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetPriceFeed<'info> {
+    #[account(seeds = [b"protocol_config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + 32 + 4 + 32 * PriceFeed::MAX_ORACLES + 1,
+        seeds = [b"price_feed", mint.key().as_ref()],
+        bump
+    )]
+    pub price_feed: Account<'info, PriceFeed>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
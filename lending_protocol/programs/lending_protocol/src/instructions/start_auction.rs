@@ -0,0 +1,182 @@
+use crate::{
+    errors::Errors,
+    oracle::{debt_for_collateral, load_feed_price},
+    state::{
+        auction::Auction,
+        borrower_profile::BorrowerProfile,
+        collateral::CollateralVault,
+        config::ProtocolConfig,
+        loan::{LoanInfo, LoanStatus, OpenLoan},
+        price_feed::PriceFeed,
+    },
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+
+/// Defaults an overdue loan and puts its collateral up for a Dutch auction. Anyone can start
+/// the auction. The lot starts at a premium over the larger of the amount owed and, for
+/// priced loans, the collateral's oracle value, and decays over the configured window to
+/// the reserve share of the collateral value. Unpriced lots have no reserve. After the
+/// window the price keeps falling to zero, see `Auction::current_price`. The collateral
+/// stays in the vault until a bid settles the auction.
+///
+/// For priced loans the oracles of the loan mint and then of the collateral mint are
+/// passed as remaining accounts.
+pub fn start_auction(ctx: Context<StartAuction>) -> Result<()> {
+    ctx.accounts.open_loan.require_active()?;
+
+    let clock = Clock::get()?;
+    let config = &ctx.accounts.config;
+    let repay_by_time = config.effective_repay_by(
+        ctx.accounts.open_loan.repay_by_time,
+        &ctx.accounts.loan_info.loan_token_mint,
+        &ctx.accounts.loan_info.collateral_token_mint,
+    );
+    require!(
+        clock.unix_timestamp > repay_by_time,
+        Errors::LoanNotOverdue
+    );
+
+    // Interest stops accruing once the loan defaults
+    let interest = ctx.accounts.open_loan
+        .accrued_interest(ctx.accounts.loan_info.interest_rate_bps, clock.unix_timestamp)?;
+    let amount_owed = ctx.accounts.open_loan.outstanding_principal
+        .checked_add(interest)
+        .ok_or(Errors::MathOverflow)?;
+    let collateral_amount = ctx.accounts.collateral_vault.amount;
+
+    let collateral_value = if ctx.accounts.loan_info.has_price_checks() {
+        let (Some(loan_price_feed), Some(collateral_price_feed)) = (
+            ctx.accounts.loan_price_feed.as_ref(),
+            ctx.accounts.collateral_price_feed.as_ref(),
+        ) else {
+            return err!(Errors::MissingPriceFeed);
+        };
+        let (loan_oracles, collateral_oracles) = ctx.remaining_accounts
+            .split_at_checked(loan_price_feed.oracles.len())
+            .ok_or(Errors::MissingPriceFeed)?;
+        debt_for_collateral(
+            collateral_amount,
+            &load_feed_price(loan_price_feed, loan_oracles, config, clock.unix_timestamp)?,
+            ctx.accounts.loan_token_mint.decimals,
+            &load_feed_price(collateral_price_feed, collateral_oracles, config, clock.unix_timestamp)?,
+            ctx.accounts.collateral_token_mint.decimals,
+        )?
+    } else {
+        0
+    };
+    let reference_value = amount_owed.max(collateral_value) as u128;
+    let start_price = reference_value
+        .checked_mul(10_000 + config.auction_start_premium_bps as u128)
+        .map(|value| value / 10_000)
+        .and_then(|value| u64::try_from(value).ok())
+        .ok_or(Errors::MathOverflow)?;
+    // The reserve protects what the collateral is worth, not what the borrower owed
+    let reserve_price = (collateral_value as u128 * config.auction_reserve_bps as u128 / 10_000) as u64;
+
+    let auction = &mut ctx.accounts.auction;
+    auction.open_loan = ctx.accounts.open_loan.key();
+    auction.loan_info = ctx.accounts.loan_info.key();
+    auction.collateral_vault = ctx.accounts.collateral_vault.key();
+    auction.lender = ctx.accounts.loan_info.lender;
+    auction.borrower = ctx.accounts.borrower.key();
+    auction.started_by = ctx.accounts.payer.key();
+    auction.collateral_amount = collateral_amount;
+    auction.amount_owed = amount_owed;
+    auction.start_price = start_price;
+    auction.reserve_price = reserve_price;
+    auction.start_time = clock.unix_timestamp;
+    auction.end_time = clock.unix_timestamp
+        .checked_add(config.auction_duration_seconds as i64)
+        .ok_or(Errors::MathOverflow)?;
+    auction.bump = ctx.bumps.auction;
+
+    ctx.accounts.open_loan.transition(LoanStatus::Defaulted)?;
+    let borrower_profile = &mut ctx.accounts.borrower_profile;
+    borrower_profile.touch(ctx.accounts.borrower.key(), ctx.bumps.borrower_profile);
+    borrower_profile.record_default()?;
+
+    msg!(
+        "Loan defaulted: auctioning {} collateral of borrower {} from {} down to {}, {} owed",
+        collateral_amount,
+        ctx.accounts.borrower.key(),
+        start_price,
+        reserve_price,
+        amount_owed
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct StartAuction<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + 32 + 32 + 32 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 1,
+        seeds = [b"auction", open_loan.key().as_ref()],
+        bump
+    )]
+    /// Dutch auction for the loan's collateral
+    pub auction: Box<Account<'info, Auction>>,
+
+    #[account(
+        mut,
+        seeds = [b"open_loan", loan_info.key().as_ref(), borrower.key().as_ref()],
+        bump = open_loan.bump
+    )]
+    pub open_loan: Account<'info, OpenLoan>,
+
+    #[account(
+        has_one = loan_token_mint,
+        has_one = collateral_token_mint,
+        seeds = [
+            b"loan_info",
+            loan_info.lender.as_ref(),
+            loan_token_mint.key().as_ref(),
+            &loan_info.offer_id.to_le_bytes()
+        ],
+        bump = loan_info.bump
+    )]
+    pub loan_info: Box<Account<'info, LoanInfo>>,
+
+    #[account(
+        seeds = [b"collateral_vault", loan_info.key().as_ref(), borrower.key().as_ref()],
+        bump = collateral_vault.bump
+    )]
+    pub collateral_vault: Account<'info, CollateralVault>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + 32 + 8 + 8 + 8 + 8 + 8 + 1,
+        seeds = [b"borrower_profile", borrower.key().as_ref()],
+        bump
+    )]
+    /// On-chain history of the borrower, created on first use
+    pub borrower_profile: Account<'info, BorrowerProfile>,
+
+    #[account(seeds = [b"price_feed", loan_token_mint.key().as_ref()], bump = loan_price_feed.bump)]
+    /// Oracle registration for the loan mint, only needed for priced offers
+    pub loan_price_feed: Option<Box<Account<'info, PriceFeed>>>,
+
+    #[account(seeds = [b"price_feed", collateral_token_mint.key().as_ref()], bump = collateral_price_feed.bump)]
+    /// Oracle registration for the collateral mint, only needed for priced offers
+    pub collateral_price_feed: Option<Box<Account<'info, PriceFeed>>>,
+
+    #[account(seeds = [b"protocol_config"], bump = config.bump)]
+    pub config: Box<Account<'info, ProtocolConfig>>,
+
+    pub loan_token_mint: Box<InterfaceAccount<'info, Mint>>,
+    pub collateral_token_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    /// Pays the auction rent, refunded when the auction settles
+    pub payer: Signer<'info>,
+
+    #[account(constraint = open_loan.borrower == borrower.key())]
+    /// CHECK: Only used for PDA derivation, checked against the open loan
+    pub borrower: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
@@ -1,94 +1,222 @@
 use crate::{
+    attestation::load_score_attestation,
     errors::Errors,
-    state::{loan::{OpenLoan, LoanInfo}, collateral::CollateralVault},
+    instructions::create_loan::unwrap_sol,
+    oracle::{load_feed_price, loan_to_value_bps},
+    token_utils::{amount_with_fee, transfer_tokens},
+    state::{loan::{OpenLoan, LoanInfo, LoanStatus, OfferStatus}, collateral::CollateralVault, borrower_profile::BorrowerProfile, config::ProtocolConfig, price_feed::PriceFeed},
 };
 use anchor_lang::prelude::*;
 use anchor_spl::{
-    token::Transfer,
     token_interface::{Mint, TokenAccount, TokenInterface},
     associated_token::AssociatedToken
 };
 use std::str::FromStr;
 
-pub fn take_loan(ctx: Context<TakeLoan>) -> Result<()> {
+pub fn take_loan(ctx: Context<TakeLoan>, amount: u64) -> Result<()> {
+    ctx.accounts.config.require_borrows_open(
+        &ctx.accounts.loan_info.loan_token_mint,
+        &ctx.accounts.loan_info.collateral_token_mint,
+    )?;
+    ctx.accounts.loan_info.require_open()?;
+    let clock = Clock::get()?;
+    require!(
+        clock.unix_timestamp <= ctx.accounts.loan_info.expires_at,
+        Errors::LoanOfferExpired
+    );
+    // The last draw may be below the minimum ticket so no dust gets stuck in the vault
+    require!(amount <= ctx.accounts.loan_info.remaining_amount, Errors::InvalidLoanAmount);
+    require!(
+        amount == ctx.accounts.loan_info.remaining_amount
+            || (amount >= ctx.accounts.loan_info.min_ticket && amount <= ctx.accounts.loan_info.max_ticket),
+        Errors::InvalidTicketSize
+    );
+
+    // Once a score authority is configured, gated offers need its attestation. A fresh
+    // wallet's empty history must not stand in for it, and a stale attestation cannot
+    // outrank on-chain defaults recorded since, so the lower of the two counts.
+    let score_authority = ctx.accounts.config.score_authority;
+    let attestation = load_score_attestation(&ctx.accounts.instructions_sysvar, &score_authority)?;
+    let profile_score = ctx.accounts.borrower_profile.score();
+    let score = match attestation {
+        Some(attestation) => {
+            require_keys_eq!(attestation.borrower, ctx.accounts.borrower.key(), Errors::InvalidScoreAttestation);
+            require!(clock.unix_timestamp <= attestation.expires_at, Errors::ScoreAttestationExpired);
+            attestation.score.min(profile_score)
+        }
+        None => {
+            require!(
+                score_authority == Pubkey::default() || ctx.accounts.loan_info.min_score == 0,
+                Errors::MissingScoreAttestation
+            );
+            profile_score
+        }
+    };
+    require!(score >= ctx.accounts.loan_info.min_score, Errors::InsufficientScore);
+
+    let collateral_amount = ctx.accounts.loan_info.collateral_for(amount)?;
+
+    // Priced offers only lend while the collateral is worth enough at current prices. The
+    // oracles of the loan mint and then of the collateral mint come in as remaining accounts.
+    if ctx.accounts.loan_info.has_price_checks() {
+        let accounts = &ctx.accounts;
+        let (Some(loan_price_feed), Some(collateral_price_feed)) = (
+            accounts.loan_price_feed.as_ref(),
+            accounts.collateral_price_feed.as_ref(),
+        ) else {
+            return err!(Errors::MissingPriceFeed);
+        };
+        let (loan_oracles, collateral_oracles) = ctx.remaining_accounts
+            .split_at_checked(loan_price_feed.oracles.len())
+            .ok_or(Errors::MissingPriceFeed)?;
+        let ltv_bps = loan_to_value_bps(
+            amount,
+            &load_feed_price(loan_price_feed, loan_oracles, &accounts.config, clock.unix_timestamp)?,
+            accounts.loan_token_mint.decimals,
+            collateral_amount,
+            &load_feed_price(collateral_price_feed, collateral_oracles, &accounts.config, clock.unix_timestamp)?,
+            accounts.collateral_token_mint.decimals,
+        )?;
+        require!(
+            ltv_bps <= accounts.loan_info.max_ltv_bps as u64,
+            Errors::LoanToValueTooHigh
+        );
+    }
+
+    // SOL collateral also funds the rent exempt reserve of its escrow
+    let escrow_reserve = Rent::get()?.minimum_balance(0);
+
     // The loaner wants sol as collateral
     if ctx.accounts.loan_info.collateral_token_mint.key() == Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap() {
         require!(
-            ctx.accounts.borrower.lamports() >= ctx.accounts.loan_info.collateral_amount,
+            ctx.accounts.borrower.lamports() >= collateral_amount.checked_add(escrow_reserve).ok_or(Errors::MathOverflow)?,
             Errors::CollateralNotEnough
         );
     } else {
         // The loaner wants tokens as collateral
+        let borrower_collateral_token_account = ctx.accounts.borrower_collateral_token_account
+            .as_ref()
+            .ok_or(Errors::MissingBorrowerTokenAccount)?;
         require!(
-            ctx.accounts.borrower_token_account.amount >= ctx.accounts.loan_info.collateral_amount,
+            borrower_collateral_token_account.amount >= amount_with_fee(&ctx.accounts.collateral_token_mint, collateral_amount)?,
             Errors::CollateralNotEnough
         );
-        require!(
-            ctx.accounts.token_mint.key() == ctx.accounts.loan_info.collateral_token_mint.key(),
-            Errors::InvalidCollateralToken
-        );
     }
-    require!(!ctx.accounts.loan_info.is_active, Errors::OfferNotActive);
-    // FIXME: always passes
-    let clock = Clock::get()?;
-    require!(
-        clock.unix_timestamp <= ctx.accounts.loan_info.duration_seconds as i64 + clock.unix_timestamp,
-        Errors::LoanOfferExpired
-    );
-    require!(
-      !ctx.accounts.loan_info.is_active,
-      Errors::LoanAlreadyExists
-    );
+
+    // Transfer loan amount from vault to borrower. The vault only holds the offer's
+    // principal, so a transfer fee comes out of the payout and the loan is booked at
+    // what the borrower actually received.
+    let received = if ctx.accounts.loan_info.loan_token_mint == Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap() {
+        // Native SOL loans are paid out unwrapped
+        let unwrap_account = ctx.accounts.unwrap_account
+            .as_ref()
+            .ok_or(Errors::MissingUnwrapAccount)?;
+        unwrap_sol(
+            &ctx.accounts.loan_info,
+            &ctx.accounts.vault,
+            unwrap_account,
+            &ctx.accounts.loan_token_mint,
+            &ctx.accounts.borrower.to_account_info(),
+            &ctx.accounts.token_program,
+            amount,
+        )?;
+        amount
+    } else {
+        let borrower_loan_token_account = ctx.accounts.borrower_loan_token_account
+            .as_ref()
+            .ok_or(Errors::MissingBorrowerTokenAccount)?;
+        let lender = ctx.accounts.lender.key();
+        let loan_token_mint = ctx.accounts.loan_info.loan_token_mint;
+        let offer_id = ctx.accounts.loan_info.offer_id.to_le_bytes();
+        let seeds = &[
+            b"loan_info",
+            lender.as_ref(),
+            loan_token_mint.as_ref(),
+            offer_id.as_ref(),
+            &[ctx.accounts.loan_info.bump]
+        ];
+        let signer = &[&seeds[..]];
+        transfer_tokens(
+            &ctx.accounts.vault.to_account_info(),
+            &borrower_loan_token_account.to_account_info(),
+            &ctx.accounts.loan_token_mint,
+            &ctx.accounts.loan_info.to_account_info(),
+            &ctx.accounts.token_program,
+            amount,
+            signer,
+        )?
+    };
+    require!(received > 0, Errors::InvalidLoanAmount);
 
     // Update loan info
     let loan_info = &mut ctx.accounts.loan_info;
-    loan_info.is_active = true;
+    loan_info.remaining_amount = loan_info.remaining_amount.checked_sub(amount).ok_or(Errors::MathOverflow)?;
+    loan_info.outstanding_principal = loan_info.outstanding_principal.checked_add(received).ok_or(Errors::MathOverflow)?;
+    if loan_info.remaining_amount == 0 {
+        loan_info.transition(OfferStatus::Filled)?;
+    }
 
     // Open the loan
     let open_loan = &mut ctx.accounts.open_loan;
     open_loan.loan_info = ctx.accounts.loan_info.key();
     open_loan.borrower = ctx.accounts.borrower.key();
-    open_loan.principal = ctx.accounts.loan_info.loan_amount;
+    open_loan.principal = received;
+    open_loan.outstanding_principal = received;
     open_loan.start_time = ctx.accounts.clock.unix_timestamp;
+    open_loan.last_accrual_time = ctx.accounts.clock.unix_timestamp;
     // FIXME: Casting
     open_loan.repay_by_time = ctx.accounts.clock.unix_timestamp + ctx.accounts.loan_info.duration_seconds as i64;
-    open_loan.is_repaid = false;
+    open_loan.status = LoanStatus::Active;
     open_loan.bump = ctx.bumps.open_loan;
 
+    let borrower_profile = &mut ctx.accounts.borrower_profile;
+    borrower_profile.touch(ctx.accounts.borrower.key(), ctx.bumps.borrower_profile);
+    borrower_profile.record_loan_taken(received)?;
+
     // Deposit the collateral
 
     // The loaner wants sol as collateral
-    if ctx.accounts.loan_info.collateral_token_mint.key() == Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap() {
+    let deposited = if ctx.accounts.loan_info.collateral_token_mint.key() == Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap() {
+        let sol_escrow = ctx.accounts.sol_escrow.as_ref().ok_or(Errors::MissingSolEscrow)?;
         let ix = solana_program::system_instruction::transfer(
             &ctx.accounts.borrower.key(),
-            &ctx.accounts.collateral_vault.key(),
-            ctx.accounts.loan_info.collateral_amount,
+            &sol_escrow.key(),
+            collateral_amount + escrow_reserve,
         );
         solana_program::program::invoke(
             &ix,
             &[
                 ctx.accounts.borrower.to_account_info(),
-                ctx.accounts.collateral_vault.to_account_info(),
+                sol_escrow.to_account_info(),
             ],
         )?;
+        collateral_amount
     } else {
         // The loaner wants tokens as collateral
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.borrower_token_account.to_account_info(),
-            to: ctx.accounts.collateral_vault.to_account_info(),
-            authority: ctx.accounts.borrower.to_account_info(),
-        };
-        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
-        anchor_spl::token::transfer(cpi_ctx, ctx.accounts.loan_info.collateral_amount)?;
-    }
+        let borrower_collateral_token_account = ctx.accounts.borrower_collateral_token_account
+            .as_ref()
+            .ok_or(Errors::MissingBorrowerTokenAccount)?;
+        let collateral_token_account = ctx.accounts.collateral_token_account
+            .as_ref()
+            .ok_or(Errors::MissingCollateralVaultTokenAccount)?;
+        // Send enough on top to cover the transfer fee, then record what arrived
+        transfer_tokens(
+            &borrower_collateral_token_account.to_account_info(),
+            &collateral_token_account.to_account_info(),
+            &ctx.accounts.collateral_token_mint,
+            &ctx.accounts.borrower.to_account_info(),
+            &ctx.accounts.token_program,
+            amount_with_fee(&ctx.accounts.collateral_token_mint, collateral_amount)?,
+            &[],
+        )?
+    };
 
     // Create the collateral vault
     let collateral_vault = &mut ctx.accounts.collateral_vault;
     collateral_vault.borrower = ctx.accounts.borrower.key();
-    collateral_vault.token_mint= ctx.accounts.token_mint.key();
+    collateral_vault.token_mint = ctx.accounts.collateral_token_mint.key();
     collateral_vault.loan_info = ctx.accounts.loan_info.key();
-    collateral_vault.amount = ctx.accounts.loan_info.collateral_amount;
-    collateral_vault.is_active = true;
+    collateral_vault.amount = deposited;
     collateral_vault.bump = ctx.bumps.collateral_vault;
 
     msg!(
@@ -98,41 +226,48 @@ pub fn take_loan(ctx: Context<TakeLoan>) -> Result<()> {
         collateral_vault.token_mint
     );
 
-    // Transfer loan amount from vault to borrower
-    let lender = ctx.accounts.lender.key();
-    let token_mint = ctx.accounts.token_mint.key();
-    let seeds = &[
-        b"loan_info",
-        lender.as_ref(),
-        token_mint.as_ref(),
-        &[ctx.bumps.loan_info]
-    ];
-    let signer = &[&seeds[..]];
-    let cpi_accounts = Transfer {
-        from: ctx.accounts.vault.to_account_info(),
-        to: ctx.accounts.borrower_token_account.to_account_info(),
-        authority: ctx.accounts.loan_info.to_account_info(),
-    };
-    let cpi_program = ctx.accounts.token_program.to_account_info();
-    let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
-    anchor_spl::token::transfer(cpi_ctx, ctx.accounts.loan_info.loan_amount)?;
-
     msg!(
         "Loan taken: Borrower {} received {} from offer {}",
         ctx.accounts.borrower.key(),
-        ctx.accounts.loan_info.loan_amount,
+        received,
         ctx.accounts.loan_info.key()
     );
 
     Ok(())
 }
 
+/// Moves lamports out of the SOL collateral escrow of `collateral_vault`. The escrow keeps
+/// a rent exempt reserve on top of the collateral, so it has to be drained in full or
+/// left with at least that reserve.
+pub(crate) fn transfer_from_sol_escrow<'info>(
+    sol_escrow: &SystemAccount<'info>,
+    to: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    collateral_vault: &Pubkey,
+    bump: u8,
+    amount: u64,
+) -> Result<()> {
+    let seeds = &[b"sol_escrow", collateral_vault.as_ref(), &[bump]];
+    let signer = &[&seeds[..]];
+    let ix = solana_program::system_instruction::transfer(&sol_escrow.key(), &to.key(), amount);
+    solana_program::program::invoke_signed(
+        &ix,
+        &[
+            sol_escrow.to_account_info(),
+            to.clone(),
+            system_program.to_account_info(),
+        ],
+        signer,
+    )?;
+    Ok(())
+}
+
 #[derive(Accounts)]
 pub struct TakeLoan<'info> {
     #[account(
         init,
         payer = borrower,
-        space = 8 + 32 + 32 + 8 + 8 + 8 + 1 + 1,
+        space = 8 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 1 + 1,
         seeds = [b"open_loan", loan_info.key().as_ref(), borrower.key().as_ref()],
         bump
     )]
@@ -142,25 +277,63 @@ pub struct TakeLoan<'info> {
     #[account(
         init,
         payer = borrower,
-        space = 8 + 32 + 32 + 32 + 8 + 1 + 1,
+        space = 8 + 32 + 32 + 32 + 8 + 1,
         seeds = [b"collateral_vault", loan_info.key().as_ref(), borrower.key().as_ref()],
         bump,
     )]
     /// Stores the collateral token and metadata
     pub collateral_vault: Account<'info, CollateralVault>,
 
+    #[account(
+        init,
+        payer = borrower,
+        token::mint = collateral_token_mint,
+        token::authority = collateral_vault,
+        seeds = [b"collateral_token_account", collateral_vault.key().as_ref()],
+        bump
+    )]
+    /// Holds SPL collateral for the loan, unused for SOL collateral
+    pub collateral_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds = [b"sol_escrow", collateral_vault.key().as_ref()],
+        bump
+    )]
+    /// System owned PDA holding SOL collateral, unused for SPL collateral
+    pub sol_escrow: Option<SystemAccount<'info>>,
+
     #[account(
         init_if_needed,
         payer = borrower,
-        associated_token::mint = token_mint,
+        space = 8 + 32 + 8 + 8 + 8 + 8 + 8 + 1,
+        seeds = [b"borrower_profile", borrower.key().as_ref()],
+        bump
+    )]
+    /// On-chain history of the borrower, created on first use
+    pub borrower_profile: Account<'info, BorrowerProfile>,
+
+    #[account(
+        init_if_needed,
+        payer = borrower,
+        associated_token::mint = loan_token_mint,
         associated_token::authority = borrower,
         associated_token::token_program = token_program,
     )]
-    pub borrower_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// Receives the borrowed tokens, unused when borrowing native SOL
+    pub borrower_loan_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
 
     #[account(
         mut,
-        token::mint = loan_info.loan_token_mint,
+        token::mint = collateral_token_mint,
+        token::authority = borrower
+    )]
+    /// Source of SPL collateral, unused for SOL collateral
+    pub borrower_collateral_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        token::mint = loan_token_mint,
         token::authority = loan_info,
         seeds = [b"vault", loan_info.key().as_ref()],
         bump
@@ -169,13 +342,42 @@ pub struct TakeLoan<'info> {
     pub vault: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
-        mut,
-        seeds = [b"loan_info", lender.key().as_ref(), token_mint.key().as_ref()],
+        init,
+        payer = borrower,
+        token::mint = loan_token_mint,
+        token::authority = loan_info,
+        seeds = [b"unwrap", open_loan.key().as_ref()],
         bump
     )]
+    /// Temporary WSOL account used to pay out native SOL, closed in the same instruction
+    pub unwrap_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        has_one = loan_token_mint,
+        has_one = collateral_token_mint,
+        seeds = [
+            b"loan_info",
+            lender.key().as_ref(),
+            loan_token_mint.key().as_ref(),
+            &loan_info.offer_id.to_le_bytes()
+        ],
+        bump = loan_info.bump
+    )]
     /// Stores metadata about the loan info
     pub loan_info: Account<'info, LoanInfo>,
 
+    #[account(seeds = [b"protocol_config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+
+    #[account(seeds = [b"price_feed", loan_token_mint.key().as_ref()], bump = loan_price_feed.bump)]
+    /// Oracle registration for the loan mint, only needed for priced offers
+    pub loan_price_feed: Option<Box<Account<'info, PriceFeed>>>,
+
+    #[account(seeds = [b"price_feed", collateral_token_mint.key().as_ref()], bump = collateral_price_feed.bump)]
+    /// Oracle registration for the collateral mint, only needed for priced offers
+    pub collateral_price_feed: Option<Box<Account<'info, PriceFeed>>>,
+
     #[account(mut)]
     pub borrower: Signer<'info>,
 
@@ -183,10 +385,15 @@ pub struct TakeLoan<'info> {
     /// CHECK: lender is only used for loan info pda derivation
     pub lender: AccountInfo<'info>,
     /// Token being lent
-    pub token_mint: InterfaceAccount<'info, Mint>,
+    pub loan_token_mint: Box<InterfaceAccount<'info, Mint>>,
+    /// Token accepted as collateral
+    pub collateral_token_mint: Box<InterfaceAccount<'info, Mint>>,
     pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
     pub clock: Sysvar<'info, Clock>,
+    #[account(address = solana_program::sysvar::instructions::ID)]
+    /// CHECK: Instructions sysvar, read for an optional credit score attestation
+    pub instructions_sysvar: AccountInfo<'info>,
 }
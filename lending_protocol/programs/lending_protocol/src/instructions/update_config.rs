@@ -0,0 +1,233 @@
+use crate::{
+    errors::Errors,
+    state::config::{MintPause, PauseWindow, ProtocolConfig},
+};
+use anchor_lang::prelude::*;
+
+pub fn set_score_authority(ctx: Context<UpdateConfig>, score_authority: Pubkey) -> Result<()> {
+    ctx.accounts.config.score_authority = score_authority;
+    msg!("Score authority set to {}", score_authority);
+    Ok(())
+}
+
+pub fn set_protocol_fee(ctx: Context<UpdateConfig>, protocol_fee_bps: u16) -> Result<()> {
+    require!(protocol_fee_bps <= 10_000, Errors::InvalidProtocolFee);
+    ctx.accounts.config.protocol_fee_bps = protocol_fee_bps;
+    msg!("Protocol fee set to {} bps", protocol_fee_bps);
+    Ok(())
+}
+
+/// Repayment and collateral withdrawal stay open regardless of the pause flags. Pausing
+/// new borrows opens a pause window that extends repayment deadlines, since borrowers
+/// cannot roll their loans over during it. Pausing new offers does not.
+pub fn set_pause_flags(
+    ctx: Context<UpdateConfig>,
+    pause_new_offers: bool,
+    pause_new_borrows: bool,
+) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    let now = Clock::get()?.unix_timestamp;
+    let grace_period_seconds = config.grace_period_seconds;
+    if pause_new_borrows && !config.pause_new_borrows {
+        config.borrow_pause.open(now, grace_period_seconds);
+    } else if !pause_new_borrows && config.pause_new_borrows {
+        config.borrow_pause.close(now);
+    }
+    config.pause_new_offers = pause_new_offers;
+    config.pause_new_borrows = pause_new_borrows;
+    msg!(
+        "Pause flags set: new offers {}, new borrows {}",
+        pause_new_offers,
+        pause_new_borrows
+    );
+    Ok(())
+}
+
+/// Each mint keeps its own pause window, so only loans against that mint get the grace
+/// extension
+pub fn pause_mint(ctx: Context<UpdateConfig>, mint: Pubkey) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    let now = Clock::get()?.unix_timestamp;
+    let grace_period_seconds = config.grace_period_seconds;
+    // Windows past their grace period no longer extend any deadline
+    config.mint_pauses.retain(|pause| !pause.window.is_expired(now, grace_period_seconds));
+    match config.mint_pauses.iter_mut().find(|pause| pause.mint == mint) {
+        Some(pause) => {
+            require!(!pause.window.is_open(), Errors::MintAlreadyPaused);
+            pause.window.open(now, grace_period_seconds);
+        }
+        None => {
+            require!(
+                config.mint_pauses.len() < ProtocolConfig::MAX_PAUSED_MINTS,
+                Errors::PausedMintsFull
+            );
+            let mut window = PauseWindow::default();
+            window.open(now, grace_period_seconds);
+            config.mint_pauses.push(MintPause { mint, window });
+        }
+    }
+    msg!("Mint {} paused", mint);
+    Ok(())
+}
+
+pub fn unpause_mint(ctx: Context<UpdateConfig>, mint: Pubkey) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    let pause = config.mint_pauses
+        .iter_mut()
+        .find(|pause| pause.mint == mint && pause.window.is_open())
+        .ok_or(Errors::MintNotPaused)?;
+    pause.window.close(Clock::get()?.unix_timestamp);
+    msg!("Mint {} unpaused", mint);
+    Ok(())
+}
+
+pub fn set_grace_period(ctx: Context<UpdateConfig>, grace_period_seconds: u64) -> Result<()> {
+    ctx.accounts.config.grace_period_seconds = grace_period_seconds;
+    msg!("Pause grace period set to {} seconds", grace_period_seconds);
+    Ok(())
+}
+
+pub fn set_oracle_limits(
+    ctx: Context<UpdateConfig>,
+    max_price_age_seconds: u64,
+    max_confidence_bps: u16,
+) -> Result<()> {
+    require!(
+        max_price_age_seconds > 0 && max_confidence_bps > 0 && max_confidence_bps <= 10_000,
+        Errors::InvalidOracleLimits
+    );
+    let config = &mut ctx.accounts.config;
+    config.max_price_age_seconds = max_price_age_seconds;
+    config.max_confidence_bps = max_confidence_bps;
+    msg!(
+        "Oracle limits set: max age {} seconds, max confidence {} bps",
+        max_price_age_seconds,
+        max_confidence_bps
+    );
+    Ok(())
+}
+
+pub fn set_liquidation_params(
+    ctx: Context<UpdateConfig>,
+    close_factor_bps: u16,
+    liquidation_bonus_bps: u16,
+) -> Result<()> {
+    require!(
+        close_factor_bps > 0 && close_factor_bps <= 10_000 && liquidation_bonus_bps < 10_000,
+        Errors::InvalidLiquidationParams
+    );
+    let config = &mut ctx.accounts.config;
+    config.close_factor_bps = close_factor_bps;
+    config.liquidation_bonus_bps = liquidation_bonus_bps;
+    msg!(
+        "Liquidation params set: close factor {} bps, bonus {} bps",
+        close_factor_bps,
+        liquidation_bonus_bps
+    );
+    Ok(())
+}
+
+pub fn set_auction_params(
+    ctx: Context<UpdateConfig>,
+    auction_duration_seconds: u64,
+    auction_start_premium_bps: u16,
+    auction_reserve_bps: u16,
+) -> Result<()> {
+    require!(
+        auction_duration_seconds > 0
+            && auction_duration_seconds <= i64::MAX as u64
+            && auction_reserve_bps <= 10_000,
+        Errors::InvalidAuctionParams
+    );
+    let config = &mut ctx.accounts.config;
+    config.auction_duration_seconds = auction_duration_seconds;
+    config.auction_start_premium_bps = auction_start_premium_bps;
+    config.auction_reserve_bps = auction_reserve_bps;
+    msg!(
+        "Auction params set: duration {} seconds, start premium {} bps, reserve {} bps",
+        auction_duration_seconds,
+        auction_start_premium_bps,
+        auction_reserve_bps
+    );
+    Ok(())
+}
+
+pub fn set_limits(
+    ctx: Context<UpdateConfig>,
+    max_loan_amount: u64,
+    max_duration_seconds: u64,
+) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    config.max_loan_amount = max_loan_amount;
+    config.max_duration_seconds = max_duration_seconds;
+    msg!(
+        "Limits set to max loan amount {} and max duration {} seconds",
+        max_loan_amount,
+        max_duration_seconds
+    );
+    Ok(())
+}
+
+pub fn add_allowed_mint(ctx: Context<UpdateConfig>, mint: Pubkey) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    require!(!config.allowed_mints.contains(&mint), Errors::MintAlreadyAllowed);
+    require!(
+        config.allowed_mints.len() < ProtocolConfig::MAX_ALLOWED_MINTS,
+        Errors::AllowedMintsFull
+    );
+    config.allowed_mints.push(mint);
+    msg!("Mint {} allowed", mint);
+    Ok(())
+}
+
+pub fn remove_allowed_mint(ctx: Context<UpdateConfig>, mint: Pubkey) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    let index = config.allowed_mints
+        .iter()
+        .position(|allowed| *allowed == mint)
+        .ok_or(Errors::MintNotAllowed)?;
+    config.allowed_mints.swap_remove(index);
+    msg!("Mint {} removed from allowed mints", mint);
+    Ok(())
+}
+
+/// First step of a two step handover, the new admin has to accept
+pub fn transfer_admin(ctx: Context<UpdateConfig>, new_admin: Pubkey) -> Result<()> {
+    ctx.accounts.config.pending_admin = new_admin;
+    msg!("Admin transfer to {} pending", new_admin);
+    Ok(())
+}
+
+pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    config.admin = ctx.accounts.pending_admin.key();
+    config.pending_admin = Pubkey::default();
+    msg!("Admin transferred to {}", config.admin);
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(
+        mut,
+        has_one = admin,
+        seeds = [b"protocol_config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    #[account(
+        mut,
+        has_one = pending_admin @ Errors::NotPendingAdmin,
+        seeds = [b"protocol_config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+
+    pub pending_admin: Signer<'info>,
+}
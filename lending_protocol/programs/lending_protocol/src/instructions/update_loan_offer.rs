@@ -0,0 +1,185 @@
+use crate::{
+    errors::Errors,
+    instructions::create_loan::{unwrap_sol, validate_loan_terms, wrap_sol},
+    state::{loan::LoanInfo, config::ProtocolConfig},
+    token_utils::transfer_tokens,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use std::str::FromStr;
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_loan_offer(
+    ctx: Context<UpdateLoanOffer>,
+    loan_amount: u64,
+    collateral_amount: u64,
+    interest_rate_bps: u16,
+    duration_seconds: u64,
+    min_score: u64,
+    min_ticket: u64,
+    max_ticket: u64,
+) -> Result<()> {
+    ctx.accounts.loan_info.require_open()?;
+    // Terms are locked as soon as any borrower has drawn from the offer
+    require!(
+        ctx.accounts.loan_info.outstanding_principal == 0
+            && ctx.accounts.loan_info.remaining_amount == ctx.accounts.loan_info.loan_amount,
+        Errors::OfferAlreadyTaken
+    );
+    validate_loan_terms(
+        loan_amount,
+        collateral_amount,
+        interest_rate_bps,
+        duration_seconds,
+        min_score,
+        min_ticket,
+        max_ticket,
+    )?;
+    let config = &ctx.accounts.config;
+    config.require_offers_open(&ctx.accounts.loan_info.loan_token_mint, &ctx.accounts.loan_info.collateral_token_mint)?;
+    config.require_mint_allowed(&ctx.accounts.loan_info.loan_token_mint)?;
+    config.require_mint_allowed(&ctx.accounts.loan_info.collateral_token_mint)?;
+    config.require_within_limits(loan_amount, duration_seconds)?;
+
+    // Top up or withdraw principal so the vault matches the new loan amount
+    let vault_amount = ctx.accounts.vault.amount;
+    let is_loaned_sol = ctx.accounts.loan_token_mint.key() == Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap();
+    if loan_amount > vault_amount && is_loaned_sol {
+        wrap_sol(
+            &ctx.accounts.lender.to_account_info(),
+            &ctx.accounts.vault.to_account_info(),
+            &ctx.accounts.system_program,
+            &ctx.accounts.token_program,
+            loan_amount - vault_amount,
+        )?;
+    } else if loan_amount > vault_amount {
+        let top_up = loan_amount - vault_amount;
+        let lender_token_account = ctx.accounts.lender_token_account
+            .as_ref()
+            .ok_or(Errors::MissingLenderTokenAccount)?;
+        transfer_tokens(
+            &lender_token_account.to_account_info(),
+            &ctx.accounts.vault.to_account_info(),
+            &ctx.accounts.loan_token_mint,
+            &ctx.accounts.lender.to_account_info(),
+            &ctx.accounts.token_program,
+            top_up,
+            &[],
+        )?;
+    } else if loan_amount < vault_amount && is_loaned_sol {
+        let unwrap_account = ctx.accounts.unwrap_account
+            .as_ref()
+            .ok_or(Errors::MissingUnwrapAccount)?;
+        unwrap_sol(
+            &ctx.accounts.loan_info,
+            &ctx.accounts.vault,
+            unwrap_account,
+            &ctx.accounts.loan_token_mint,
+            &ctx.accounts.lender.to_account_info(),
+            &ctx.accounts.token_program,
+            vault_amount - loan_amount,
+        )?;
+    } else if loan_amount < vault_amount {
+        let withdrawal = vault_amount - loan_amount;
+        let lender_token_account = ctx.accounts.lender_token_account
+            .as_ref()
+            .ok_or(Errors::MissingLenderTokenAccount)?;
+        let lender = ctx.accounts.lender.key();
+        let loan_token_mint = ctx.accounts.loan_token_mint.key();
+        let offer_id = ctx.accounts.loan_info.offer_id.to_le_bytes();
+        let seeds = &[
+            b"loan_info",
+            lender.as_ref(),
+            loan_token_mint.as_ref(),
+            offer_id.as_ref(),
+            &[ctx.accounts.loan_info.bump],
+        ];
+        let signer = &[&seeds[..]];
+        transfer_tokens(
+            &ctx.accounts.vault.to_account_info(),
+            &lender_token_account.to_account_info(),
+            &ctx.accounts.loan_token_mint,
+            &ctx.accounts.loan_info.to_account_info(),
+            &ctx.accounts.token_program,
+            withdrawal,
+            signer,
+        )?;
+    }
+    // Top ups can lose a transfer fee on the way, the offer is sized to what the vault holds
+    ctx.accounts.vault.reload()?;
+    let funded = ctx.accounts.vault.amount;
+
+    let loan_info = &mut ctx.accounts.loan_info;
+    loan_info.loan_amount = loan_amount;
+    loan_info.collateral_amount = collateral_amount;
+    loan_info.interest_rate_bps = interest_rate_bps;
+    loan_info.duration_seconds = duration_seconds;
+    loan_info.min_score = min_score;
+    loan_info.min_ticket = min_ticket;
+    loan_info.max_ticket = max_ticket;
+    loan_info.set_funded_amount(funded)?;
+
+    msg!(
+        "Loan offer {} updated with amount: {} and interest rate: {} bps",
+        loan_info.offer_id,
+        funded,
+        interest_rate_bps
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct UpdateLoanOffer<'info> {
+    #[account(
+        mut,
+        has_one = lender,
+        seeds = [
+            b"loan_info",
+            lender.key().as_ref(),
+            loan_token_mint.key().as_ref(),
+            &loan_info.offer_id.to_le_bytes()
+        ],
+        bump = loan_info.bump
+    )]
+    /// Stores metadata about the loan info
+    pub loan_info: Account<'info, LoanInfo>,
+
+    #[account(
+        mut,
+        token::mint = loan_token_mint,
+        token::authority = loan_info,
+        seeds = [b"vault", loan_info.key().as_ref()],
+        bump
+    )]
+    /// Holds the tokens sent out for a loan prior to a second party borrowing
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = lender,
+        token::mint = loan_token_mint,
+        token::authority = loan_info,
+        seeds = [b"unwrap", loan_info.key().as_ref()],
+        bump
+    )]
+    /// Temporary WSOL account used to pay out native SOL, closed in the same instruction
+    pub unwrap_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(seeds = [b"protocol_config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    #[account(
+       mut,
+       token::mint = loan_token_mint,
+       token::authority = lender
+    )]
+    /// Tops up or receives principal, unused when lending native SOL
+    pub lender_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub loan_token_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,92 @@
+use crate::{
+    errors::Errors,
+    state::{config::ProtocolConfig, treasury::Treasury},
+    token_utils::transfer_tokens,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use std::str::FromStr;
+
+pub fn withdraw_treasury(ctx: Context<WithdrawTreasury>, amount: u64) -> Result<()> {
+    let is_sol = ctx.accounts.mint.key() == Pubkey::from_str("So11111111111111111111111111111111111111112").unwrap();
+
+    if is_sol {
+        // SOL fees sit on the treasury account itself, keep it rent exempt
+        let treasury = ctx.accounts.treasury.to_account_info();
+        let admin = ctx.accounts.admin.to_account_info();
+
+        let rent_exempt_minimum = Rent::get()?.minimum_balance(treasury.data_len());
+        let available = treasury
+            .lamports()
+            .checked_sub(rent_exempt_minimum)
+            .ok_or(Errors::MathOverflow)?;
+        require!(amount <= available, Errors::InsufficientTreasuryBalance);
+
+        **treasury.try_borrow_mut_lamports()? -= amount;
+        **admin.try_borrow_mut_lamports()? = admin
+            .lamports()
+            .checked_add(amount)
+            .ok_or(Errors::MathOverflow)?;
+    } else {
+        let treasury_token_account = ctx.accounts.treasury_token_account
+            .as_ref()
+            .ok_or(Errors::MissingTreasuryTokenAccount)?;
+        let destination_token_account = ctx.accounts.destination_token_account
+            .as_ref()
+            .ok_or(Errors::MissingTreasuryTokenAccount)?;
+        require!(amount <= treasury_token_account.amount, Errors::InsufficientTreasuryBalance);
+
+        let seeds = &[b"treasury".as_ref(), &[ctx.accounts.treasury.bump]];
+        let signer = &[&seeds[..]];
+        transfer_tokens(
+            &treasury_token_account.to_account_info(),
+            &destination_token_account.to_account_info(),
+            &ctx.accounts.mint,
+            &ctx.accounts.treasury.to_account_info(),
+            &ctx.accounts.token_program,
+            amount,
+            signer,
+        )?;
+    }
+
+    msg!(
+        "Admin {} withdrew {} of mint {} from the treasury",
+        ctx.accounts.admin.key(),
+        amount,
+        ctx.accounts.mint.key()
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct WithdrawTreasury<'info> {
+    #[account(seeds = [b"protocol_config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, ProtocolConfig>,
+
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    /// Holds SOL fees and owns the per mint treasury token accounts
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = treasury,
+        seeds = [b"treasury", mint.key().as_ref()],
+        bump
+    )]
+    /// Only used if the mint is SPL
+    pub treasury_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut, token::mint = mint)]
+    /// Only used if the mint is SPL, receives the withdrawn fees
+    pub destination_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
@@ -1,10 +1,17 @@
+// Anchor 0.31's #[program] expands to AccountInfo::realloc, deprecated since solana-account-info 2.3
+#![allow(deprecated)]
+
 use anchor_lang::prelude::*;
 
 declare_id!("FJUYYdAmHJ996KqUen7jiD2AgbP3pgTU6KzRz28Lhhhn");
 
+pub mod attestation;
 pub mod errors;
+pub mod events;
 pub mod instructions;
+pub mod oracle;
 pub mod state;
+pub mod token_utils;
 
 use instructions::*;
 
@@ -13,12 +20,101 @@ use instructions::*;
 pub mod lending_protocol {
     use super::*;
 
-    /// Testing purposes
-    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
-        msg!("Testing lending protocol: {:?}", ctx.program_id);
-        Ok(())
+    pub fn initialize(ctx: Context<Initialize>, score_authority: Pubkey) -> Result<()> {
+        instructions::initialize(ctx, score_authority)
+    }
+
+    pub fn set_score_authority(ctx: Context<UpdateConfig>, score_authority: Pubkey) -> Result<()> {
+        instructions::set_score_authority(ctx, score_authority)
     }
 
+    pub fn set_protocol_fee(ctx: Context<UpdateConfig>, protocol_fee_bps: u16) -> Result<()> {
+        instructions::set_protocol_fee(ctx, protocol_fee_bps)
+    }
+
+    pub fn set_pause_flags(
+        ctx: Context<UpdateConfig>,
+        pause_new_offers: bool,
+        pause_new_borrows: bool,
+    ) -> Result<()> {
+        instructions::set_pause_flags(ctx, pause_new_offers, pause_new_borrows)
+    }
+
+    pub fn pause_mint(ctx: Context<UpdateConfig>, mint: Pubkey) -> Result<()> {
+        instructions::pause_mint(ctx, mint)
+    }
+
+    pub fn unpause_mint(ctx: Context<UpdateConfig>, mint: Pubkey) -> Result<()> {
+        instructions::unpause_mint(ctx, mint)
+    }
+
+    pub fn set_grace_period(ctx: Context<UpdateConfig>, grace_period_seconds: u64) -> Result<()> {
+        instructions::set_grace_period(ctx, grace_period_seconds)
+    }
+
+    pub fn set_oracle_limits(
+        ctx: Context<UpdateConfig>,
+        max_price_age_seconds: u64,
+        max_confidence_bps: u16,
+    ) -> Result<()> {
+        instructions::set_oracle_limits(ctx, max_price_age_seconds, max_confidence_bps)
+    }
+
+    pub fn set_liquidation_params(
+        ctx: Context<UpdateConfig>,
+        close_factor_bps: u16,
+        liquidation_bonus_bps: u16,
+    ) -> Result<()> {
+        instructions::set_liquidation_params(ctx, close_factor_bps, liquidation_bonus_bps)
+    }
+
+    pub fn set_auction_params(
+        ctx: Context<UpdateConfig>,
+        auction_duration_seconds: u64,
+        auction_start_premium_bps: u16,
+        auction_reserve_bps: u16,
+    ) -> Result<()> {
+        instructions::set_auction_params(
+            ctx,
+            auction_duration_seconds,
+            auction_start_premium_bps,
+            auction_reserve_bps,
+        )
+    }
+
+    pub fn set_limits(
+        ctx: Context<UpdateConfig>,
+        max_loan_amount: u64,
+        max_duration_seconds: u64,
+    ) -> Result<()> {
+        instructions::set_limits(ctx, max_loan_amount, max_duration_seconds)
+    }
+
+    pub fn add_allowed_mint(ctx: Context<UpdateConfig>, mint: Pubkey) -> Result<()> {
+        instructions::add_allowed_mint(ctx, mint)
+    }
+
+    pub fn remove_allowed_mint(ctx: Context<UpdateConfig>, mint: Pubkey) -> Result<()> {
+        instructions::remove_allowed_mint(ctx, mint)
+    }
+
+    pub fn transfer_admin(ctx: Context<UpdateConfig>, new_admin: Pubkey) -> Result<()> {
+        instructions::transfer_admin(ctx, new_admin)
+    }
+
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        instructions::accept_admin(ctx)
+    }
+
+    pub fn withdraw_treasury(ctx: Context<WithdrawTreasury>, amount: u64) -> Result<()> {
+        instructions::withdraw_treasury(ctx, amount)
+    }
+
+    pub fn set_price_feed(ctx: Context<SetPriceFeed>) -> Result<()> {
+        instructions::set_price_feed(ctx)
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn create_loan(
         ctx: Context<CreateLoan>,
         loan_amount: u64,
@@ -26,6 +122,12 @@ pub mod lending_protocol {
         interest_rate_bps: u16,
         duration_slots: u64,
         min_score: u64,
+        expires_at: i64,
+        min_ticket: u64,
+        max_ticket: u64,
+        allow_partial_release: bool,
+        max_ltv_bps: u16,
+        liquidation_threshold_bps: u16,
     ) -> Result<()> {
         instructions::create_loan(
             ctx,
@@ -34,17 +136,97 @@ pub mod lending_protocol {
             interest_rate_bps,
             duration_slots,
             min_score,
+            expires_at,
+            min_ticket,
+            max_ticket,
+            allow_partial_release,
+            max_ltv_bps,
+            liquidation_threshold_bps,
         )
     }
 
-    pub fn take_loan(ctx: Context<TakeLoan>) -> Result<()> {
-        instructions::take_loan(ctx)
+    pub fn take_loan(ctx: Context<TakeLoan>, amount: u64) -> Result<()> {
+        instructions::take_loan(ctx, amount)
     }
 
-    pub fn pay_loan(ctx: Context<PayLoan>) -> Result<()> {
-        instructions::pay_loan(ctx)
+    pub fn pay_loan(ctx: Context<PayLoan>, repay_amount: u64) -> Result<()> {
+        instructions::pay_loan(ctx, repay_amount)
     }
-}
 
-#[derive(Accounts)]
-pub struct Initialize {}
+    pub fn add_collateral(ctx: Context<AddCollateral>, amount: u64) -> Result<()> {
+        instructions::add_collateral(ctx, amount)
+    }
+
+    pub fn create_loan_request(
+        ctx: Context<CreateLoanRequest>,
+        request_id: u64,
+        loan_amount: u64,
+        collateral_amount: u64,
+        max_interest_rate_bps: u16,
+        duration_seconds: u64,
+    ) -> Result<()> {
+        instructions::create_loan_request(
+            ctx,
+            request_id,
+            loan_amount,
+            collateral_amount,
+            max_interest_rate_bps,
+            duration_seconds,
+        )
+    }
+
+    pub fn fill_loan_request(ctx: Context<FillLoanRequest>, interest_rate_bps: u16) -> Result<()> {
+        instructions::fill_loan_request(ctx, interest_rate_bps)
+    }
+
+    pub fn cancel_loan_request(ctx: Context<CancelLoanRequest>) -> Result<()> {
+        instructions::cancel_loan_request(ctx)
+    }
+
+    pub fn liquidate(ctx: Context<Liquidate>, max_repay_amount: u64) -> Result<()> {
+        instructions::liquidate(ctx, max_repay_amount)
+    }
+
+    pub fn start_auction(ctx: Context<StartAuction>) -> Result<()> {
+        instructions::start_auction(ctx)
+    }
+
+    pub fn bid(ctx: Context<Bid>, max_price: u64) -> Result<()> {
+        instructions::bid(ctx, max_price)
+    }
+
+    pub fn cancel_loan_offer(ctx: Context<CancelLoanOffer>) -> Result<()> {
+        instructions::cancel_loan_offer(ctx)
+    }
+
+    pub fn close_expired_loan_offer(ctx: Context<CloseExpiredLoanOffer>) -> Result<()> {
+        instructions::close_expired_loan_offer(ctx)
+    }
+
+    pub fn close_loan_offer(ctx: Context<CloseLoanOffer>) -> Result<()> {
+        instructions::close_loan_offer(ctx)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_loan_offer(
+        ctx: Context<UpdateLoanOffer>,
+        loan_amount: u64,
+        collateral_amount: u64,
+        interest_rate_bps: u16,
+        duration_seconds: u64,
+        min_score: u64,
+        min_ticket: u64,
+        max_ticket: u64,
+    ) -> Result<()> {
+        instructions::update_loan_offer(
+            ctx,
+            loan_amount,
+            collateral_amount,
+            interest_rate_bps,
+            duration_seconds,
+            min_score,
+            min_ticket,
+            max_ticket,
+        )
+    }
+}
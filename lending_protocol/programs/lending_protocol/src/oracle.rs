@@ -0,0 +1,460 @@
+//! Prices for loan and collateral mints, read from Pyth compatible price accounts.
+//!
+//! Each mint is mapped to up to three oracle accounts by a `PriceFeed` PDA the admin sets
+//! up, so instructions only trust the oracles registered for the mint they are pricing.
+//! Every source has to be trading, fresh and tight enough under the limits in the
+//! protocol config. Sources that fail are skipped as long as a majority still passes,
+//! and the median of the remaining prices is used.
+//!
+//! Only the fields the protocol needs are read from the Pyth v2 price account layout:
+//! magic, version and account type in the header, the exponent, the publish timestamp
+//! and the aggregate price, confidence and status.
+
+use crate::{
+    errors::Errors,
+    state::{config::ProtocolConfig, price_feed::PriceFeed},
+};
+use anchor_lang::prelude::*;
+
+pub const PYTH_MAGIC: u32 = 0xa1b2_c3d4;
+pub const PYTH_VERSION: u32 = 2;
+pub const PYTH_PRICE_ACCOUNT_TYPE: u32 = 3;
+/// Aggregate status of a price that is currently being published
+pub const PYTH_STATUS_TRADING: u32 = 1;
+
+const MAGIC_OFFSET: usize = 0;
+const VERSION_OFFSET: usize = 4;
+const ACCOUNT_TYPE_OFFSET: usize = 8;
+const EXPO_OFFSET: usize = 20;
+const TIMESTAMP_OFFSET: usize = 96;
+const AGG_PRICE_OFFSET: usize = 208;
+const AGG_CONF_OFFSET: usize = 216;
+const AGG_STATUS_OFFSET: usize = 224;
+const MIN_ACCOUNT_LEN: usize = AGG_STATUS_OFFSET + 4;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct OraclePrice {
+    pub price: i64,         // Aggregate price, scaled by 10^expo
+    pub conf: u64,          // Confidence interval, same scale as price
+    pub expo: i32,          // Decimal exponent of price and conf
+    pub publish_time: i64,  // Unix timestamp of the aggregate price
+    pub status: u32,        // Aggregate status, PYTH_STATUS_TRADING when usable
+}
+
+/// Median price of the oracles registered in `price_feed`. `oracles` are the accounts
+/// passed in by the caller, in the order they were registered.
+pub fn load_feed_price(
+    price_feed: &PriceFeed,
+    oracles: &[AccountInfo],
+    config: &ProtocolConfig,
+    now: i64,
+) -> Result<OraclePrice> {
+    require!(oracles.len() == price_feed.oracles.len(), Errors::MissingPriceFeed);
+
+    let mut prices = Vec::with_capacity(oracles.len());
+    let mut last_error = None;
+    for (oracle, expected) in oracles.iter().zip(&price_feed.oracles) {
+        require_keys_eq!(oracle.key(), *expected, Errors::PriceFeedMismatch);
+        let price = load_pyth_price(oracle).and_then(|price| {
+            check_price(&price, now, config.max_price_age_seconds, config.max_confidence_bps)?;
+            Ok(price)
+        });
+        match price {
+            Ok(price) => prices.push(price),
+            Err(error) => {
+                msg!("Skipping oracle {}", oracle.key());
+                last_error = Some(error);
+            }
+        }
+    }
+
+    // A majority of the sources has to be usable before the price is trusted
+    if prices.len() * 2 <= oracles.len() {
+        return Err(last_error.unwrap_or_else(|| Errors::NotEnoughPriceSources.into()));
+    }
+    median_price(&mut prices)
+}
+
+/// Reads the aggregate price of a Pyth v2 price account without judging it
+pub fn load_pyth_price(oracle: &AccountInfo) -> Result<OraclePrice> {
+    parse_price_account(&oracle.try_borrow_data()?)
+}
+
+pub fn parse_price_account(data: &[u8]) -> Result<OraclePrice> {
+    require!(data.len() >= MIN_ACCOUNT_LEN, Errors::InvalidOracleAccount);
+    require!(
+        read_u32(data, MAGIC_OFFSET) == PYTH_MAGIC
+            && read_u32(data, VERSION_OFFSET) == PYTH_VERSION
+            && read_u32(data, ACCOUNT_TYPE_OFFSET) == PYTH_PRICE_ACCOUNT_TYPE,
+        Errors::InvalidOracleAccount
+    );
+
+    Ok(OraclePrice {
+        price: read_i64(data, AGG_PRICE_OFFSET),
+        conf: read_u64(data, AGG_CONF_OFFSET),
+        expo: read_u32(data, EXPO_OFFSET) as i32,
+        publish_time: read_i64(data, TIMESTAMP_OFFSET),
+        status: read_u32(data, AGG_STATUS_OFFSET),
+    })
+}
+
+/// Rejects prices that are not trading, older than `max_age_seconds` or whose confidence
+/// interval is wider than `max_confidence_bps` of the price
+pub fn check_price(
+    price: &OraclePrice,
+    now: i64,
+    max_age_seconds: u64,
+    max_confidence_bps: u16,
+) -> Result<()> {
+    require!(
+        price.status == PYTH_STATUS_TRADING && price.price > 0,
+        Errors::OraclePriceUnavailable
+    );
+    // Publish times slightly ahead of the cluster clock count as fresh
+    let age = now.saturating_sub(price.publish_time).max(0) as u64;
+    require!(age <= max_age_seconds, Errors::StalePrice);
+    require!(
+        (price.conf as u128) * 10_000 <= (price.price as u128) * max_confidence_bps as u128,
+        Errors::PriceConfidenceTooWide
+    );
+    Ok(())
+}
+
+/// Median of `prices` after bringing them to the smallest exponent among them. With an
+/// even count the two middle prices are averaged and the wider confidence is kept.
+pub fn median_price(prices: &mut [OraclePrice]) -> Result<OraclePrice> {
+    require!(!prices.is_empty(), Errors::NotEnoughPriceSources);
+
+    let expo = prices.iter().map(|price| price.expo).min().unwrap();
+    for price in prices.iter_mut() {
+        let scale = 10i128
+            .checked_pow((price.expo - expo) as u32)
+            .ok_or(Errors::MathOverflow)?;
+        price.price = i64::try_from(price.price as i128 * scale).map_err(|_| Errors::MathOverflow)?;
+        price.conf = u64::try_from(price.conf as i128 * scale).map_err(|_| Errors::MathOverflow)?;
+        price.expo = expo;
+    }
+    prices.sort_by_key(|price| price.price);
+
+    let middle = prices.len() / 2;
+    if prices.len() % 2 == 1 {
+        return Ok(prices[middle]);
+    }
+    let (low, high) = (prices[middle - 1], prices[middle]);
+    Ok(OraclePrice {
+        price: ((low.price as i128 + high.price as i128) / 2) as i64,
+        conf: low.conf.max(high.conf),
+        expo,
+        publish_time: low.publish_time.min(high.publish_time),
+        status: PYTH_STATUS_TRADING,
+    })
+}
+
+/// Loan to value in basis points of `debt` units of the loan mint against `collateral`
+/// units of the collateral mint. Uncollateralized debt is reported as `u64::MAX`.
+pub fn loan_to_value_bps(
+    debt: u64,
+    loan_price: &OraclePrice,
+    loan_decimals: u8,
+    collateral: u64,
+    collateral_price: &OraclePrice,
+    collateral_decimals: u8,
+) -> Result<u64> {
+    // debt * p_l * 10^(e_l - d_l) / (collateral * p_c * 10^(e_c - d_c)), with the powers of
+    // ten folded into whichever side keeps the exponent positive
+    let exponent = loan_price.expo as i64 - loan_decimals as i64 - collateral_price.expo as i64
+        + collateral_decimals as i64;
+    let scale = 10u128
+        .checked_pow(u32::try_from(exponent.unsigned_abs()).map_err(|_| Errors::MathOverflow)?)
+        .ok_or(Errors::MathOverflow)?;
+
+    let mut numerator = (debt as u128)
+        .checked_mul(loan_price.price as u128)
+        .and_then(|value| value.checked_mul(10_000))
+        .ok_or(Errors::MathOverflow)?;
+    let mut denominator = (collateral as u128)
+        .checked_mul(collateral_price.price as u128)
+        .ok_or(Errors::MathOverflow)?;
+    if exponent >= 0 {
+        numerator = numerator.checked_mul(scale).ok_or(Errors::MathOverflow)?;
+    } else {
+        denominator = denominator.checked_mul(scale).ok_or(Errors::MathOverflow)?;
+    }
+
+    if denominator == 0 {
+        return Ok(u64::MAX);
+    }
+    Ok(u64::try_from(numerator.div_ceil(denominator)).unwrap_or(u64::MAX))
+}
+
+/// Units of the collateral mint worth `debt` units of the loan mint, plus `bonus_bps` on
+/// top, rounded down
+pub fn collateral_for_debt(
+    debt: u64,
+    loan_price: &OraclePrice,
+    loan_decimals: u8,
+    collateral_price: &OraclePrice,
+    collateral_decimals: u8,
+    bonus_bps: u16,
+) -> Result<u64> {
+    let exponent = loan_price.expo as i64 - loan_decimals as i64 - collateral_price.expo as i64
+        + collateral_decimals as i64;
+    let scale = 10u128
+        .checked_pow(u32::try_from(exponent.unsigned_abs()).map_err(|_| Errors::MathOverflow)?)
+        .ok_or(Errors::MathOverflow)?;
+
+    let mut numerator = (debt as u128)
+        .checked_mul(loan_price.price as u128)
+        .and_then(|value| value.checked_mul(10_000 + bonus_bps as u128))
+        .ok_or(Errors::MathOverflow)?;
+    let mut denominator = (collateral_price.price as u128)
+        .checked_mul(10_000)
+        .ok_or(Errors::MathOverflow)?;
+    if exponent >= 0 {
+        numerator = numerator.checked_mul(scale).ok_or(Errors::MathOverflow)?;
+    } else {
+        denominator = denominator.checked_mul(scale).ok_or(Errors::MathOverflow)?;
+    }
+    Ok(u64::try_from(numerator / denominator).unwrap_or(u64::MAX))
+}
+
+/// Units of the loan mint worth `collateral` units of the collateral mint, rounded down
+pub fn debt_for_collateral(
+    collateral: u64,
+    loan_price: &OraclePrice,
+    loan_decimals: u8,
+    collateral_price: &OraclePrice,
+    collateral_decimals: u8,
+) -> Result<u64> {
+    // The inverse of collateral_for_debt without a bonus
+    collateral_for_debt(collateral, collateral_price, collateral_decimals, loan_price, loan_decimals, 0)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+fn read_i64(data: &[u8], offset: usize) -> i64 {
+    i64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+    const PRICE_ACCOUNT_LEN: usize = 3312;
+
+    /// Bytes of a Pyth v2 price account holding the given aggregate price
+    fn price_account(price: i64, conf: u64, expo: i32, publish_time: i64, status: u32) -> Vec<u8> {
+        let mut data = vec![0u8; PRICE_ACCOUNT_LEN];
+        data[MAGIC_OFFSET..MAGIC_OFFSET + 4].copy_from_slice(&PYTH_MAGIC.to_le_bytes());
+        data[VERSION_OFFSET..VERSION_OFFSET + 4].copy_from_slice(&PYTH_VERSION.to_le_bytes());
+        data[ACCOUNT_TYPE_OFFSET..ACCOUNT_TYPE_OFFSET + 4]
+            .copy_from_slice(&PYTH_PRICE_ACCOUNT_TYPE.to_le_bytes());
+        data[EXPO_OFFSET..EXPO_OFFSET + 4].copy_from_slice(&expo.to_le_bytes());
+        data[TIMESTAMP_OFFSET..TIMESTAMP_OFFSET + 8].copy_from_slice(&publish_time.to_le_bytes());
+        data[AGG_PRICE_OFFSET..AGG_PRICE_OFFSET + 8].copy_from_slice(&price.to_le_bytes());
+        data[AGG_CONF_OFFSET..AGG_CONF_OFFSET + 8].copy_from_slice(&conf.to_le_bytes());
+        data[AGG_STATUS_OFFSET..AGG_STATUS_OFFSET + 4].copy_from_slice(&status.to_le_bytes());
+        data
+    }
+
+    fn trading(price: i64, expo: i32) -> OraclePrice {
+        OraclePrice { price, conf: 0, expo, publish_time: NOW, status: PYTH_STATUS_TRADING }
+    }
+
+    fn config() -> ProtocolConfig {
+        ProtocolConfig {
+            admin: Pubkey::default(),
+            pending_admin: Pubkey::default(),
+            score_authority: Pubkey::default(),
+            protocol_fee_bps: 0,
+            pause_new_offers: false,
+            pause_new_borrows: false,
+            mint_pauses: Vec::new(),
+            allowed_mints: Vec::new(),
+            max_loan_amount: 0,
+            max_duration_seconds: 0,
+            borrow_pause: Default::default(),
+            grace_period_seconds: 0,
+            max_price_age_seconds: ProtocolConfig::DEFAULT_MAX_PRICE_AGE_SECONDS,
+            max_confidence_bps: ProtocolConfig::DEFAULT_MAX_CONFIDENCE_BPS,
+            close_factor_bps: ProtocolConfig::DEFAULT_CLOSE_FACTOR_BPS,
+            liquidation_bonus_bps: ProtocolConfig::DEFAULT_LIQUIDATION_BONUS_BPS,
+            auction_duration_seconds: ProtocolConfig::DEFAULT_AUCTION_DURATION_SECONDS,
+            auction_start_premium_bps: ProtocolConfig::DEFAULT_AUCTION_START_PREMIUM_BPS,
+            auction_reserve_bps: ProtocolConfig::DEFAULT_AUCTION_RESERVE_BPS,
+            bump: 0,
+        }
+    }
+
+    /// Runs `load_feed_price` over synthetic accounts owned by a random program
+    fn feed_price(accounts: Vec<Vec<u8>>) -> Result<OraclePrice> {
+        let owner = Pubkey::new_unique();
+        let keys: Vec<Pubkey> = accounts.iter().map(|_| Pubkey::new_unique()).collect();
+        let mut lamports = vec![0u64; accounts.len()];
+        let mut data = accounts;
+        let infos: Vec<AccountInfo> = keys
+            .iter()
+            .zip(lamports.iter_mut())
+            .zip(data.iter_mut())
+            .map(|((key, lamports), data)| {
+                AccountInfo::new(key, false, false, lamports, data, &owner, false, 0)
+            })
+            .collect();
+        let price_feed = PriceFeed { mint: Pubkey::new_unique(), oracles: keys.clone(), bump: 0 };
+        load_feed_price(&price_feed, &infos, &config(), NOW)
+    }
+
+    fn error_code(result: Result<OraclePrice>) -> u32 {
+        match result.unwrap_err() {
+            Error::AnchorError(error) => error.error_code_number,
+            error => panic!("unexpected error {error:?}"),
+        }
+    }
+
+    fn code(error: Errors) -> u32 {
+        error as u32 + anchor_lang::error::ERROR_CODE_OFFSET
+    }
+
+    #[test]
+    fn parses_price_account() {
+        let data = price_account(12_345, 6, -8, NOW, PYTH_STATUS_TRADING);
+        let price = parse_price_account(&data).unwrap();
+        assert_eq!(price, OraclePrice { price: 12_345, conf: 6, expo: -8, publish_time: NOW, status: 1 });
+    }
+
+    #[test]
+    fn rejects_foreign_accounts() {
+        let mut data = price_account(12_345, 6, -8, NOW, PYTH_STATUS_TRADING);
+        data[MAGIC_OFFSET] ^= 0xff;
+        assert!(parse_price_account(&data).is_err());
+        assert!(parse_price_account(&[0u8; 64]).is_err());
+    }
+
+    #[test]
+    fn checks_status_age_and_confidence() {
+        let fresh = trading(100_000_000, -8);
+        assert!(check_price(&fresh, NOW, 60, 200).is_ok());
+
+        let halted = OraclePrice { status: 0, ..fresh };
+        assert!(check_price(&halted, NOW, 60, 200).is_err());
+
+        let stale = OraclePrice { publish_time: NOW - 61, ..fresh };
+        assert!(check_price(&stale, NOW, 60, 200).is_err());
+        let ahead = OraclePrice { publish_time: NOW + 5, ..fresh };
+        assert!(check_price(&ahead, NOW, 60, 200).is_ok());
+
+        let at_limit = OraclePrice { conf: 2_000_000, ..fresh };
+        assert!(check_price(&at_limit, NOW, 60, 200).is_ok());
+        let wide = OraclePrice { conf: 2_000_001, ..fresh };
+        assert!(check_price(&wide, NOW, 60, 200).is_err());
+    }
+
+    #[test]
+    fn median_of_odd_and_even_counts() {
+        let mut prices = [trading(300, -2), trading(100, -2), trading(200, -2)];
+        assert_eq!(median_price(&mut prices).unwrap().price, 200);
+
+        let mut prices = [trading(300, -2), trading(100, -2)];
+        assert_eq!(median_price(&mut prices).unwrap().price, 200);
+    }
+
+    #[test]
+    fn median_normalizes_exponents() {
+        // 1.00, 1.10 and 0.95 published with different exponents
+        let mut prices = [trading(100, -2), trading(1_100, -3), trading(95_000, -5)];
+        let median = median_price(&mut prices).unwrap();
+        assert_eq!((median.price, median.expo), (100_000, -5));
+    }
+
+    #[test]
+    fn feed_uses_median_of_sources() {
+        let price = feed_price(vec![
+            price_account(101_000_000, 0, -8, NOW, PYTH_STATUS_TRADING),
+            price_account(99_000_000, 0, -8, NOW, PYTH_STATUS_TRADING),
+            price_account(100_000_000, 0, -8, NOW, PYTH_STATUS_TRADING),
+        ])
+        .unwrap();
+        assert_eq!(price.price, 100_000_000);
+    }
+
+    #[test]
+    fn feed_skips_a_bad_source_out_of_three() {
+        let price = feed_price(vec![
+            price_account(101_000_000, 0, -8, NOW, PYTH_STATUS_TRADING),
+            price_account(1, 0, -8, NOW - 3_600, PYTH_STATUS_TRADING),
+            price_account(99_000_000, 0, -8, NOW, PYTH_STATUS_TRADING),
+        ])
+        .unwrap();
+        assert_eq!(price.price, 100_000_000);
+    }
+
+    #[test]
+    fn feed_reports_why_a_single_source_failed() {
+        let stale = feed_price(vec![price_account(100, 0, -2, NOW - 61, PYTH_STATUS_TRADING)]);
+        assert_eq!(error_code(stale), code(Errors::StalePrice));
+
+        let wide = feed_price(vec![price_account(100, 3, -2, NOW, PYTH_STATUS_TRADING)]);
+        assert_eq!(error_code(wide), code(Errors::PriceConfidenceTooWide));
+
+        let halted = feed_price(vec![price_account(100, 0, -2, NOW, 0)]);
+        assert_eq!(error_code(halted), code(Errors::OraclePriceUnavailable));
+    }
+
+    #[test]
+    fn feed_needs_a_majority_of_sources() {
+        let result = feed_price(vec![
+            price_account(100, 0, -2, NOW, PYTH_STATUS_TRADING),
+            price_account(100, 0, -2, NOW - 61, PYTH_STATUS_TRADING),
+        ]);
+        assert_eq!(error_code(result), code(Errors::StalePrice));
+    }
+
+    #[test]
+    fn loan_to_value_across_decimals() {
+        // 1 token (6 decimals) at $1 against 0.2 token (9 decimals) at $10
+        let ltv = loan_to_value_bps(
+            1_000_000,
+            &trading(100_000_000, -8),
+            6,
+            200_000_000,
+            &trading(1_000_000_000, -8),
+            9,
+        )
+        .unwrap();
+        assert_eq!(ltv, 5_000);
+
+        let seized = collateral_for_debt(
+            500_000,
+            &trading(100_000_000, -8),
+            6,
+            &trading(500_000_000, -8),
+            9,
+            500,
+        )
+        .unwrap();
+        // $0.50 of debt at a 5% bonus is $0.525, or 0.105 of a $5 token
+        assert_eq!(seized, 105_000_000);
+
+        // 0.2 of a $5 token is worth $1
+        let value = debt_for_collateral(
+            200_000_000,
+            &trading(100_000_000, -8),
+            6,
+            &trading(500_000_000, -8),
+            9,
+        )
+        .unwrap();
+        assert_eq!(value, 1_000_000);
+
+        let uncollateralized =
+            loan_to_value_bps(1, &trading(1, 0), 0, 0, &trading(1, 0), 0).unwrap();
+        assert_eq!(uncollateralized, u64::MAX);
+    }
+}
@@ -0,0 +1,95 @@
+use anchor_lang::prelude::*;
+
+#[account]
+pub struct Auction{
+    pub open_loan: Pubkey,          // Defaulted loan whose collateral is being sold
+    pub loan_info: Pubkey,          // Offer the loan was drawn from
+    pub collateral_vault: Pubkey,   // Vault still holding the collateral until a bid lands
+    pub lender: Pubkey,             // Receives the proceeds up to amount_owed
+    pub borrower: Pubkey,           // Receives any proceeds above amount_owed
+    pub started_by: Pubkey,         // Paid the auction rent, refunded when it settles
+    pub collateral_amount: u64,     // Collateral sold as a single lot
+    pub amount_owed: u64,           // Principal and interest at default, in the loan mint
+    pub start_price: u64,           // Price of the lot when the auction starts, in the loan mint
+    pub reserve_price: u64,         // Price of the lot at end_time, a share of the collateral value
+    pub start_time: i64,            // Unix timestamp the auction started
+    pub end_time: i64,              // Unix timestamp the price reaches the reserve
+    pub bump: u8,                   // PDA bump seed
+}
+
+impl Auction {
+    /// Price of the lot at `now`. It decays linearly from start_price to reserve_price at
+    /// end_time, then from the reserve to zero over a second window of the same length, so
+    /// a lot nobody bids on at the reserve still sells and the loan gets settled.
+    pub fn current_price(&self, now: i64) -> u64 {
+        let duration = self.end_time - self.start_time;
+        if now < self.end_time {
+            Self::decay(self.start_price, self.reserve_price, self.end_time - now.max(self.start_time), duration)
+        } else {
+            let remaining = self.end_time.saturating_add(duration).saturating_sub(now).max(0);
+            Self::decay(self.reserve_price, 0, remaining, duration)
+        }
+    }
+
+    /// Linear decay from `from` down to `to`, with `remaining` of `duration` left to go
+    fn decay(from: u64, to: u64, remaining: i64, duration: i64) -> u64 {
+        if duration <= 0 {
+            return to;
+        }
+        let decaying = from.saturating_sub(to) as u128;
+        to + (decaying * remaining as u128 / duration as u128) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auction(start_price: u64, reserve_price: u64) -> Auction {
+        Auction {
+            open_loan: Pubkey::default(),
+            loan_info: Pubkey::default(),
+            collateral_vault: Pubkey::default(),
+            lender: Pubkey::default(),
+            borrower: Pubkey::default(),
+            started_by: Pubkey::default(),
+            collateral_amount: 0,
+            amount_owed: 0,
+            start_price,
+            reserve_price,
+            start_time: 1_000,
+            end_time: 2_000,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn price_decays_linearly_to_the_reserve() {
+        let auction = auction(1_500, 500);
+        assert_eq!(auction.current_price(1_000), 1_500);
+        assert_eq!(auction.current_price(1_500), 1_000);
+        assert_eq!(auction.current_price(1_999), 501);
+        assert_eq!(auction.current_price(2_000), 500);
+    }
+
+    #[test]
+    fn price_decays_from_the_reserve_to_zero_after_the_end() {
+        let auction = auction(1_500, 500);
+        assert_eq!(auction.current_price(2_500), 250);
+        assert_eq!(auction.current_price(2_999), 0);
+        assert_eq!(auction.current_price(3_000), 0);
+        assert_eq!(auction.current_price(i64::MAX), 0);
+    }
+
+    #[test]
+    fn unpriced_lot_decays_straight_to_zero() {
+        let auction = auction(1_500, 0);
+        assert_eq!(auction.current_price(1_500), 750);
+        assert_eq!(auction.current_price(2_000), 0);
+    }
+
+    #[test]
+    fn price_holds_before_start() {
+        assert_eq!(auction(1_500, 500).current_price(0), 1_500);
+    }
+}
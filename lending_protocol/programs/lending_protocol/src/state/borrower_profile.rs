@@ -0,0 +1,61 @@
+use anchor_lang::prelude::*;
+use crate::errors::Errors;
+
+#[account]
+pub struct BorrowerProfile{
+    pub borrower: Pubkey,           // Borrower’s public key
+    pub loans_taken: u64,           // Loans opened through take_loan or fill_loan_request
+    pub loans_repaid_on_time: u64,  // Loans settled by repay_by_time
+    pub loans_repaid_late: u64,     // Loans settled after repay_by_time
+    pub loans_defaulted: u64,       // Loans whose collateral was seized
+    pub total_volume: u64,          // Sum of principal ever borrowed
+    pub bump: u8,                   // PDA bump seed
+}
+
+impl BorrowerProfile {
+    /// Score a wallet with no history starts from. Fresh keypairs are free, so a
+    /// clean slate must never beat a poor record, score is only earned by repaying.
+    pub const BASE_SCORE: u64 = 0;
+    pub const MAX_SCORE: u64 = 1000;
+    pub const ON_TIME_BONUS: u64 = 25;
+    pub const LATE_PENALTY: u64 = 50;
+    pub const DEFAULT_PENALTY: u64 = 200;
+
+    /// Credit score on the same 0-1000 scale as LoanInfo.min_score, derived only from on-chain history
+    pub fn score(&self) -> u64 {
+        let bonus = self.loans_repaid_on_time.saturating_mul(Self::ON_TIME_BONUS);
+        let penalty = self.loans_repaid_late
+            .saturating_mul(Self::LATE_PENALTY)
+            .saturating_add(self.loans_defaulted.saturating_mul(Self::DEFAULT_PENALTY));
+        Self::BASE_SCORE
+            .saturating_add(bonus)
+            .saturating_sub(penalty)
+            .min(Self::MAX_SCORE)
+    }
+
+    /// Profiles are created lazily, so every writer stamps the owner and bump
+    pub fn touch(&mut self, borrower: Pubkey, bump: u8) {
+        self.borrower = borrower;
+        self.bump = bump;
+    }
+
+    pub fn record_loan_taken(&mut self, principal: u64) -> Result<()> {
+        self.loans_taken = self.loans_taken.checked_add(1).ok_or(Errors::MathOverflow)?;
+        self.total_volume = self.total_volume.checked_add(principal).ok_or(Errors::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn record_repayment(&mut self, on_time: bool) -> Result<()> {
+        if on_time {
+            self.loans_repaid_on_time = self.loans_repaid_on_time.checked_add(1).ok_or(Errors::MathOverflow)?;
+        } else {
+            self.loans_repaid_late = self.loans_repaid_late.checked_add(1).ok_or(Errors::MathOverflow)?;
+        }
+        Ok(())
+    }
+
+    pub fn record_default(&mut self) -> Result<()> {
+        self.loans_defaulted = self.loans_defaulted.checked_add(1).ok_or(Errors::MathOverflow)?;
+        Ok(())
+    }
+}
@@ -6,6 +6,5 @@ pub struct CollateralVault{
     pub token_mint: Pubkey,
     pub loan_info: Pubkey,
     pub amount: u64,
-    pub is_active: bool,
     pub bump: u8,
 }
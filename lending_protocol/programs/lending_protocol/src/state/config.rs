@@ -0,0 +1,254 @@
+use anchor_lang::prelude::*;
+use crate::errors::Errors;
+
+/// Time span of a pause. Loans maturing inside it get until its end plus the grace period.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct PauseWindow {
+    pub started_at: i64,    // Start of the window, 0 if never paused
+    pub ended_at: i64,      // End of the window, 0 while the pause is ongoing
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MintPause {
+    pub mint: Pubkey,           // Paused mint, as loan or collateral
+    pub window: PauseWindow,    // Open while the mint is paused
+}
+
+#[account]
+pub struct ProtocolConfig{
+    pub admin: Pubkey,              // Authority allowed to change the config
+    pub pending_admin: Pubkey,      // Nominated admin until they accept, default when none
+    pub score_authority: Pubkey,    // Key that signs off-chain credit score attestations
+    pub protocol_fee_bps: u16,      // Protocol fee in basis points
+    pub pause_new_offers: bool,     // Blocks new and updated loan offers
+    pub pause_new_borrows: bool,    // Blocks new borrows and loan requests
+    pub mint_pauses: Vec<MintPause>, // Mints blocked for new offers and borrows, kept until their grace period is over
+    pub allowed_mints: Vec<Pubkey>, // Mints usable as loan or collateral, empty allows any
+    pub max_loan_amount: u64,       // Largest principal per offer or request, 0 for no limit
+    pub max_duration_seconds: u64,  // Longest loan duration, 0 for no limit
+    pub borrow_pause: PauseWindow,  // Latest window in which new borrows were paused
+    pub grace_period_seconds: u64,  // Extra time after a pause for loans that matured during it
+    pub max_price_age_seconds: u64, // Oracle prices older than this are rejected
+    pub max_confidence_bps: u16,    // Widest oracle confidence interval accepted, relative to the price
+    pub close_factor_bps: u16,      // Largest share of a loan's debt one liquidation can repay
+    pub liquidation_bonus_bps: u16, // Extra collateral value liquidators receive over what they repay
+    pub auction_duration_seconds: u64, // Time for a collateral auction price to decay to its reserve
+    pub auction_start_premium_bps: u16, // Markup over the debt or collateral value auctions start at
+    pub auction_reserve_bps: u16,   // Share of the collateral value an auction price reaches at its end_time
+    pub bump: u8,                   // PDA bump seed
+}
+
+impl ProtocolConfig {
+    pub const MAX_ALLOWED_MINTS: usize = 16;
+    pub const MAX_PAUSED_MINTS: usize = 8;
+    pub const DEFAULT_MAX_PRICE_AGE_SECONDS: u64 = 60;
+    pub const DEFAULT_MAX_CONFIDENCE_BPS: u16 = 200;
+    pub const DEFAULT_CLOSE_FACTOR_BPS: u16 = 5_000;
+    pub const DEFAULT_LIQUIDATION_BONUS_BPS: u16 = 500;
+    pub const DEFAULT_AUCTION_DURATION_SECONDS: u64 = 86_400;
+    pub const DEFAULT_AUCTION_START_PREMIUM_BPS: u16 = 5_000;
+    pub const DEFAULT_AUCTION_RESERVE_BPS: u16 = 5_000;
+
+    pub fn require_offers_open(&self, loan_mint: &Pubkey, collateral_mint: &Pubkey) -> Result<()> {
+        require!(!self.pause_new_offers, Errors::OffersPaused);
+        self.require_mints_not_paused(loan_mint, collateral_mint)
+    }
+
+    pub fn require_borrows_open(&self, loan_mint: &Pubkey, collateral_mint: &Pubkey) -> Result<()> {
+        require!(!self.pause_new_borrows, Errors::BorrowsPaused);
+        self.require_mints_not_paused(loan_mint, collateral_mint)
+    }
+
+    fn require_mints_not_paused(&self, loan_mint: &Pubkey, collateral_mint: &Pubkey) -> Result<()> {
+        require!(
+            !self.is_mint_paused(loan_mint) && !self.is_mint_paused(collateral_mint),
+            Errors::MintPaused
+        );
+        Ok(())
+    }
+
+    pub fn is_mint_paused(&self, mint: &Pubkey) -> bool {
+        self.mint_pauses
+            .iter()
+            .any(|pause| pause.mint == *mint && pause.window.is_open())
+    }
+
+    /// Repayment deadline after the pause grace extension. Only pauses that keep the
+    /// borrower from rolling the loan over count: new borrows being paused, or the loan's
+    /// own loan or collateral mint. Paused offers or unrelated mints leave it alone.
+    pub fn effective_repay_by(&self, repay_by_time: i64, loan_mint: &Pubkey, collateral_mint: &Pubkey) -> i64 {
+        self.mint_pauses
+            .iter()
+            .filter(|pause| pause.mint == *loan_mint || pause.mint == *collateral_mint)
+            .map(|pause| &pause.window)
+            .chain([&self.borrow_pause])
+            .map(|window| window.extend(repay_by_time, self.grace_period_seconds))
+            .fold(repay_by_time, i64::max)
+    }
+
+    pub fn require_mint_allowed(&self, mint: &Pubkey) -> Result<()> {
+        require!(
+            self.allowed_mints.is_empty() || self.allowed_mints.contains(mint),
+            Errors::MintNotAllowed
+        );
+        Ok(())
+    }
+
+    pub fn require_within_limits(&self, loan_amount: u64, duration_seconds: u64) -> Result<()> {
+        require!(
+            self.max_loan_amount == 0 || loan_amount <= self.max_loan_amount,
+            Errors::LoanAmountAboveLimit
+        );
+        require!(
+            self.max_duration_seconds == 0 || duration_seconds <= self.max_duration_seconds,
+            Errors::DurationAboveLimit
+        );
+        Ok(())
+    }
+}
+
+impl PauseWindow {
+    pub fn is_open(&self) -> bool {
+        self.started_at != 0 && self.ended_at == 0
+    }
+
+    /// Whether the window is closed and its grace period is over, so it extends nothing
+    pub fn is_expired(&self, now: i64, grace_period_seconds: u64) -> bool {
+        !self.is_open() && now > self.ended_at.saturating_add(grace_period_seconds as i64)
+    }
+
+    /// Starts a pause. One that starts within the grace period of the previous window
+    /// extends that window instead.
+    pub fn open(&mut self, now: i64, grace_period_seconds: u64) {
+        if self.started_at == 0 || self.is_expired(now, grace_period_seconds) {
+            self.started_at = now;
+        }
+        self.ended_at = 0;
+    }
+
+    pub fn close(&mut self, now: i64) {
+        self.ended_at = now;
+    }
+
+    /// Loans maturing during an ongoing pause cannot be liquidated until it ends plus the
+    /// grace period
+    pub fn extend(&self, repay_by_time: i64, grace_period_seconds: u64) -> i64 {
+        if self.started_at == 0 || repay_by_time < self.started_at {
+            return repay_by_time;
+        }
+        if self.ended_at == 0 {
+            return i64::MAX;
+        }
+        if repay_by_time <= self.ended_at {
+            return self.ended_at.saturating_add(grace_period_seconds as i64);
+        }
+        repay_by_time
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRACE: u64 = 100;
+
+    fn config() -> ProtocolConfig {
+        ProtocolConfig {
+            admin: Pubkey::default(),
+            pending_admin: Pubkey::default(),
+            score_authority: Pubkey::default(),
+            protocol_fee_bps: 0,
+            pause_new_offers: false,
+            pause_new_borrows: false,
+            mint_pauses: Vec::new(),
+            allowed_mints: Vec::new(),
+            max_loan_amount: 0,
+            max_duration_seconds: 0,
+            borrow_pause: PauseWindow::default(),
+            grace_period_seconds: 0,
+            max_price_age_seconds: ProtocolConfig::DEFAULT_MAX_PRICE_AGE_SECONDS,
+            max_confidence_bps: ProtocolConfig::DEFAULT_MAX_CONFIDENCE_BPS,
+            close_factor_bps: ProtocolConfig::DEFAULT_CLOSE_FACTOR_BPS,
+            liquidation_bonus_bps: ProtocolConfig::DEFAULT_LIQUIDATION_BONUS_BPS,
+            auction_duration_seconds: ProtocolConfig::DEFAULT_AUCTION_DURATION_SECONDS,
+            auction_start_premium_bps: ProtocolConfig::DEFAULT_AUCTION_START_PREMIUM_BPS,
+            auction_reserve_bps: ProtocolConfig::DEFAULT_AUCTION_RESERVE_BPS,
+            bump: 0,
+        }
+    }
+
+    fn window(started_at: i64, ended_at: i64) -> PauseWindow {
+        PauseWindow { started_at, ended_at }
+    }
+
+    #[test]
+    fn never_paused_keeps_deadline() {
+        assert_eq!(PauseWindow::default().extend(1_000, GRACE), 1_000);
+    }
+
+    #[test]
+    fn loan_maturing_before_pause_keeps_deadline() {
+        assert_eq!(window(1_000, 0).extend(999, GRACE), 999);
+        assert_eq!(window(1_000, 2_000).extend(999, GRACE), 999);
+    }
+
+    #[test]
+    fn ongoing_pause_holds_deadline() {
+        assert_eq!(window(1_000, 0).extend(1_000, GRACE), i64::MAX);
+        assert_eq!(window(1_000, 0).extend(5_000, GRACE), i64::MAX);
+    }
+
+    #[test]
+    fn ended_pause_extends_loans_that_matured_during_it() {
+        let window = window(1_000, 2_000);
+        assert_eq!(window.extend(1_500, GRACE), 2_100);
+        assert_eq!(window.extend(2_000, GRACE), 2_100);
+        // Maturing after the pause ended is unaffected, even within the grace period
+        assert_eq!(window.extend(2_050, GRACE), 2_050);
+    }
+
+    #[test]
+    fn repausing_within_grace_extends_the_window() {
+        let mut window = window(1_000, 2_000);
+        window.open(2_100, GRACE);
+        assert_eq!(window, PauseWindow { started_at: 1_000, ended_at: 0 });
+
+        window.close(3_000);
+        assert_eq!(window.extend(1_500, GRACE), 3_100);
+    }
+
+    #[test]
+    fn repausing_after_grace_starts_a_new_window() {
+        let mut window = window(1_000, 2_000);
+        assert!(!window.is_expired(2_100, GRACE));
+        assert!(window.is_expired(2_101, GRACE));
+
+        window.open(2_101, GRACE);
+        assert_eq!(window, PauseWindow { started_at: 2_101, ended_at: 0 });
+        assert_eq!(window.extend(1_500, GRACE), 1_500);
+    }
+
+    #[test]
+    fn open_window_never_expires() {
+        assert!(!window(1_000, 0).is_expired(i64::MAX, GRACE));
+    }
+
+    #[test]
+    fn only_pauses_affecting_the_loan_extend_it() {
+        let loan_mint = Pubkey::new_unique();
+        let collateral_mint = Pubkey::new_unique();
+        let mut config = config();
+        config.grace_period_seconds = GRACE;
+        config.pause_new_offers = true;
+        config.mint_pauses.push(MintPause { mint: Pubkey::new_unique(), window: window(1_000, 0) });
+        assert_eq!(config.effective_repay_by(1_500, &loan_mint, &collateral_mint), 1_500);
+
+        config.mint_pauses.push(MintPause { mint: collateral_mint, window: window(1_000, 2_000) });
+        assert_eq!(config.effective_repay_by(1_500, &loan_mint, &collateral_mint), 2_100);
+        assert!(!config.is_mint_paused(&collateral_mint));
+
+        // The longest extension wins
+        config.borrow_pause = window(1_200, 0);
+        assert_eq!(config.effective_repay_by(1_500, &loan_mint, &collateral_mint), i64::MAX);
+    }
+}
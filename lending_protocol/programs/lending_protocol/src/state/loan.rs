@@ -1,19 +1,40 @@
 use anchor_lang::prelude::*;
+use crate::errors::Errors;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OfferStatus {
+    Open,       // Can still be drawn from
+    Filled,     // The whole loan amount has been drawn
+    Cancelled,  // Withdrawn by the lender
+    Expired,    // Closed after expires_at passed
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LoanStatus {
+    Active,     // Borrowed and not yet settled
+    Repaid,     // Principal and interest paid back
+    Defaulted,  // Overdue, collateral auctioned off to repay the lender
+    Liquidated, // Collateral sold off to cover the debt
+    Refinanced, // Replaced by a new loan
+}
 
 #[account]
 pub struct OpenLoan{
     pub loan_info: Pubkey,      // Reference to LoanInfo
     pub borrower: Pubkey,   // Borrower’s public key
     pub principal: u64,     // Borrowed amount
+    pub outstanding_principal: u64, // Principal left after partial repayments
+    pub last_accrual_time: i64, // Interest is accrued from here on the outstanding principal
     pub start_time: i64,    // Slot when loan was accepted
     pub repay_by_time: i64, // Repayment deadline
-    pub is_repaid: bool,    // Loan repayment status
+    pub status: LoanStatus, // Lifecycle state of the loan
     pub bump: u8,           // PDA bump seed
 }
 
 #[account]
 pub struct LoanInfo{
     pub lender: Pubkey,         // Lender’s public key
+    pub offer_id: u64,          // Lender scoped offer nonce, part of the PDA seeds
     pub loan_token_mint: Pubkey,     // The token being loaned
     pub collateral_token_mint: Pubkey,     // The token that is accepted as collateral TODO: Change limit to > 1
     pub loan_amount: u64,            // Amount being lent
@@ -22,6 +43,215 @@ pub struct LoanInfo{
     pub duration_seconds: u64,  // Loan duration in seconds (e.g., 30 days = 2,592,000 seconds)
     pub min_score: u64,         // Minimum borrower score
     pub vault: Pubkey,          // Vault token account (PDA)
-    pub is_active: bool,        // Offer status
+    pub created_at: i64,        // Unix timestamp the offer was posted
+    pub expires_at: i64,        // Unix timestamp after which the offer can no longer be taken
+    pub min_ticket: u64,        // Smallest amount a single borrower can draw
+    pub max_ticket: u64,        // Largest amount a single borrower can draw
+    pub remaining_amount: u64,  // Principal still available in the vault
+    pub outstanding_principal: u64, // Principal drawn by borrowers and not yet settled
+    pub allow_partial_release: bool, // Release collateral pro rata on partial repayments
+    pub max_ltv_bps: u16,       // Highest loan to value a borrower can draw at, 0 disables price checks
+    pub liquidation_threshold_bps: u16, // Loan to value at which anyone can liquidate
+    pub status: OfferStatus,    // Lifecycle state of the offer
     pub bump: u8,               // PDA bump seed
 }
+
+impl OpenLoan {
+    pub fn require_active(&self) -> Result<()> {
+        match self.status {
+            LoanStatus::Active => Ok(()),
+            LoanStatus::Repaid => err!(Errors::LoanAlreadyRepaid),
+            LoanStatus::Defaulted => err!(Errors::LoanAlreadyDefaulted),
+            LoanStatus::Liquidated => err!(Errors::LoanAlreadyLiquidated),
+            LoanStatus::Refinanced => err!(Errors::LoanAlreadyRefinanced),
+        }
+    }
+
+    /// Loans only ever leave Active, every other status is final
+    pub fn transition(&mut self, status: LoanStatus) -> Result<()> {
+        self.require_active()?;
+        require!(status != LoanStatus::Active, Errors::InvalidStatusTransition);
+        self.status = status;
+        Ok(())
+    }
+
+    /// Interest accrued on the outstanding principal since the last payment
+    pub fn accrued_interest(&self, interest_rate_bps: u16, now: i64) -> Result<u64> {
+        const SECONDS_PER_YEAR: u128 = 31_536_000;
+        let elapsed = (now - self.last_accrual_time).max(0) as u128;
+        let interest = (self.outstanding_principal as u128)
+            .checked_mul(interest_rate_bps as u128)
+            .and_then(|value| value.checked_mul(elapsed))
+            .ok_or(Errors::MathOverflow)?
+            / (SECONDS_PER_YEAR * 10_000);
+        u64::try_from(interest).map_err(|_| Errors::MathOverflow.into())
+    }
+}
+
+impl LoanInfo {
+    /// Sizes the offer to the principal that actually reached the vault, which is below the
+    /// quoted amount when the mint takes a transfer fee. Collateral is scaled with it so the
+    /// collateral ratio stays the same.
+    pub fn set_funded_amount(&mut self, funded: u64) -> Result<()> {
+        require!(funded > 0, Errors::InvalidLoanAmount);
+        if funded != self.loan_amount {
+            self.collateral_amount = self.collateral_for(funded)?;
+            self.loan_amount = funded;
+            self.max_ticket = self.max_ticket.min(funded);
+            self.min_ticket = self.min_ticket.min(self.max_ticket);
+        }
+        self.remaining_amount = funded;
+        Ok(())
+    }
+
+    pub fn require_open(&self) -> Result<()> {
+        match self.status {
+            OfferStatus::Open => Ok(()),
+            OfferStatus::Filled => err!(Errors::OfferAlreadyTaken),
+            OfferStatus::Cancelled => err!(Errors::OfferCancelled),
+            OfferStatus::Expired => err!(Errors::LoanOfferExpired),
+        }
+    }
+
+    /// Offers only ever leave Open, every other status is final
+    pub fn transition(&mut self, status: OfferStatus) -> Result<()> {
+        self.require_open()?;
+        require!(status != OfferStatus::Open, Errors::InvalidStatusTransition);
+        self.status = status;
+        Ok(())
+    }
+
+    /// Whether the offer's loans are priced through oracles
+    pub fn has_price_checks(&self) -> bool {
+        self.max_ltv_bps > 0
+    }
+
+    /// Collateral required to draw `amount`, scaled from the full offer and rounded up
+    pub fn collateral_for(&self, amount: u64) -> Result<u64> {
+        let collateral = (self.collateral_amount as u128)
+            .checked_mul(amount as u128)
+            .and_then(|value| value.checked_add(self.loan_amount as u128 - 1))
+            .and_then(|value| value.checked_div(self.loan_amount as u128))
+            .ok_or(Errors::MathOverflow)?;
+        u64::try_from(collateral).map_err(|_| Errors::MathOverflow.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offer(loan_amount: u64, collateral_amount: u64, min_ticket: u64, max_ticket: u64) -> LoanInfo {
+        LoanInfo {
+            lender: Pubkey::default(),
+            offer_id: 0,
+            loan_token_mint: Pubkey::default(),
+            collateral_token_mint: Pubkey::default(),
+            loan_amount,
+            collateral_amount,
+            interest_rate_bps: 500,
+            duration_seconds: 86_400,
+            min_score: 0,
+            vault: Pubkey::default(),
+            created_at: 0,
+            expires_at: 0,
+            min_ticket,
+            max_ticket,
+            remaining_amount: loan_amount,
+            outstanding_principal: 0,
+            allow_partial_release: false,
+            max_ltv_bps: 0,
+            liquidation_threshold_bps: 0,
+            status: OfferStatus::Open,
+            bump: 0,
+        }
+    }
+
+    const YEAR: i64 = 31_536_000;
+
+    fn loan(principal: u64, start_time: i64) -> OpenLoan {
+        OpenLoan {
+            loan_info: Pubkey::default(),
+            borrower: Pubkey::default(),
+            principal,
+            outstanding_principal: principal,
+            last_accrual_time: start_time,
+            start_time,
+            repay_by_time: start_time + YEAR,
+            status: LoanStatus::Active,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn collateral_rounds_up() {
+        let offer = offer(1_000, 2_001, 1, 1_000);
+        assert_eq!(offer.collateral_for(1_000).unwrap(), 2_001);
+        assert_eq!(offer.collateral_for(500).unwrap(), 1_001);
+        assert_eq!(offer.collateral_for(0).unwrap(), 0);
+    }
+
+    #[test]
+    fn last_ticket_dust_still_needs_collateral() {
+        // A one unit remainder would need a fraction of a collateral unit, it is rounded to one
+        let offer = offer(1_000_000, 10, 100_000, 1_000_000);
+        assert_eq!(offer.collateral_for(999_999).unwrap(), 10);
+        assert_eq!(offer.collateral_for(1).unwrap(), 1);
+    }
+
+    #[test]
+    fn funded_amount_shrinks_offer_and_tickets() {
+        let mut offer = offer(1_000, 2_000, 1_000, 1_000);
+        offer.set_funded_amount(990).unwrap();
+        assert_eq!(offer.loan_amount, 990);
+        assert_eq!(offer.remaining_amount, 990);
+        assert_eq!(offer.collateral_amount, 1_980);
+        assert_eq!(offer.max_ticket, 990);
+        assert_eq!(offer.min_ticket, 990);
+    }
+
+    #[test]
+    fn funded_amount_keeps_tickets_that_still_fit() {
+        let mut offer = offer(1_000, 2_000, 100, 500);
+        offer.set_funded_amount(990).unwrap();
+        assert_eq!(offer.min_ticket, 100);
+        assert_eq!(offer.max_ticket, 500);
+    }
+
+    #[test]
+    fn full_funding_leaves_offer_unchanged() {
+        let mut offer = offer(1_000, 2_001, 100, 1_000);
+        offer.set_funded_amount(1_000).unwrap();
+        assert_eq!(offer.collateral_amount, 2_001);
+        assert_eq!(offer.max_ticket, 1_000);
+        assert_eq!(offer.remaining_amount, 1_000);
+    }
+
+    #[test]
+    fn funded_amount_must_be_positive() {
+        assert!(offer(1_000, 2_000, 1, 1_000).set_funded_amount(0).is_err());
+    }
+
+    #[test]
+    fn interest_accrues_per_second() {
+        let loan = loan(1_000_000, 1_000);
+        assert_eq!(loan.accrued_interest(500, 1_000 + YEAR).unwrap(), 50_000);
+        assert_eq!(loan.accrued_interest(500, 1_000 + YEAR / 2).unwrap(), 25_000);
+        // Rounded down, and nothing accrues before the last accrual
+        assert_eq!(loan.accrued_interest(500, 1_001).unwrap(), 0);
+        assert_eq!(loan.accrued_interest(500, 0).unwrap(), 0);
+    }
+
+    #[test]
+    fn interest_restarts_after_partial_repayment() {
+        let mut loan = loan(1_000_000, 1_000);
+        let half_year = 1_000 + YEAR / 2;
+        assert_eq!(loan.accrued_interest(500, half_year).unwrap(), 25_000);
+
+        // Paying down half the principal settles the interest so far
+        loan.outstanding_principal = 500_000;
+        loan.last_accrual_time = half_year;
+        assert_eq!(loan.accrued_interest(500, half_year).unwrap(), 0);
+        assert_eq!(loan.accrued_interest(500, half_year + YEAR / 2).unwrap(), 12_500);
+    }
+}
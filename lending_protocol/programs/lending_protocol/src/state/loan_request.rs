@@ -0,0 +1,15 @@
+use anchor_lang::prelude::*;
+
+#[account]
+pub struct LoanRequest{
+    pub borrower: Pubkey,               // Borrower’s public key
+    pub request_id: u64,                // Borrower scoped request nonce, part of the PDA seeds
+    pub loan_token_mint: Pubkey,        // The token the borrower wants
+    pub collateral_token_mint: Pubkey,  // The token escrowed as collateral
+    pub loan_amount: u64,               // Amount requested
+    pub collateral_amount: u64,         // Collateral escrowed at creation
+    pub max_interest_rate_bps: u16,     // Highest rate the borrower accepts in basis points
+    pub duration_seconds: u64,          // Loan duration in seconds once filled
+    pub created_at: i64,                // Unix timestamp the request was posted
+    pub bump: u8,                       // PDA bump seed
+}
@@ -1,3 +1,9 @@
 pub mod borrower_profile;
 pub mod loan;
 pub mod collateral;
+pub mod offer_counter;
+pub mod loan_request;
+pub mod config;
+pub mod treasury;
+pub mod price_feed;
+pub mod auction;
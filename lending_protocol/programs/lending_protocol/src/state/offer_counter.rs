@@ -0,0 +1,8 @@
+use anchor_lang::prelude::*;
+
+#[account]
+pub struct OfferCounter{
+    pub lender: Pubkey,         // Lender’s public key
+    pub next_offer_id: u64,     // Id handed out to the lender's next loan offer
+    pub bump: u8,               // PDA bump seed
+}
@@ -0,0 +1,12 @@
+use anchor_lang::prelude::*;
+
+#[account]
+pub struct PriceFeed{
+    pub mint: Pubkey,           // Mint being priced
+    pub oracles: Vec<Pubkey>,   // Pyth compatible price accounts, the median of their prices is used
+    pub bump: u8,               // PDA bump seed
+}
+
+impl PriceFeed {
+    pub const MAX_ORACLES: usize = 3;
+}
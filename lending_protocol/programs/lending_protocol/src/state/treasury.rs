@@ -0,0 +1,6 @@
+use anchor_lang::prelude::*;
+
+#[account]
+pub struct Treasury{
+    pub bump: u8,               // PDA bump seed
+}
@@ -0,0 +1,87 @@
+//! Token helpers shared by the instructions.
+//!
+//! Transfers go through `transfer_checked` so they work for both the legacy token
+//! program and Token-2022. Token-2022 mints can take a transfer fee, so callers that
+//! credit a vault use the amount returned by `transfer_tokens`, which is what actually
+//! arrived, instead of the amount that was sent.
+
+use crate::errors::Errors;
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::accessor,
+    token_2022::spl_token_2022::{
+        self,
+        extension::{
+            transfer_fee::TransferFeeConfig, BaseStateWithExtensions, ExtensionType,
+            StateWithExtensions,
+        },
+    },
+    token_interface::{transfer_checked, Mint, TokenInterface, TransferChecked},
+};
+
+/// Transfers `amount` of `mint` and returns how much `to` received after transfer fees.
+/// Pass empty `signer_seeds` when `authority` signs the transaction itself.
+pub fn transfer_tokens<'info>(
+    from: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    mint: &InterfaceAccount<'info, Mint>,
+    authority: &AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<u64> {
+    let balance_before = accessor::amount(to)?;
+
+    let cpi_accounts = TransferChecked {
+        from: from.clone(),
+        mint: mint.to_account_info(),
+        to: to.clone(),
+        authority: authority.clone(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer_seeds);
+    transfer_checked(cpi_ctx, amount, mint.decimals)?;
+
+    let balance_after = accessor::amount(to)?;
+    balance_after
+        .checked_sub(balance_before)
+        .ok_or(Errors::MathOverflow.into())
+}
+
+/// Amount that has to be sent so that `amount` arrives after the mint's transfer fee
+pub fn amount_with_fee(mint: &InterfaceAccount<Mint>, amount: u64) -> Result<u64> {
+    let mint_info = mint.to_account_info();
+    if *mint_info.owner != spl_token_2022::ID {
+        return Ok(amount);
+    }
+
+    let data = mint_info.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    let fee = match state.get_extension::<TransferFeeConfig>() {
+        Ok(config) => config
+            .calculate_inverse_epoch_fee(Clock::get()?.epoch, amount)
+            .ok_or(Errors::MathOverflow)?,
+        Err(_) => 0,
+    };
+    amount.checked_add(fee).ok_or(Errors::MathOverflow.into())
+}
+
+/// Rejects Token-2022 mints whose extensions would let tokens leave the protocol's
+/// accounts without its signature, or that cannot be moved by a plain transfer
+pub fn require_supported_mint(mint: &InterfaceAccount<Mint>) -> Result<()> {
+    let mint_info = mint.to_account_info();
+    if *mint_info.owner != spl_token_2022::ID {
+        return Ok(());
+    }
+
+    let data = mint_info.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    for extension in state.get_extension_types()? {
+        match extension {
+            ExtensionType::NonTransferable => return err!(Errors::NonTransferableMint),
+            ExtensionType::PermanentDelegate => return err!(Errors::PermanentDelegateMint),
+            ExtensionType::TransferHook => return err!(Errors::UnsupportedMintExtension),
+            _ => {}
+        }
+    }
+    Ok(())
+}
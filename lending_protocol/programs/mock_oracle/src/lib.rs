@@ -0,0 +1,59 @@
+//! Test double for a Pyth oracle. Writes the fields of the Pyth v2 price account layout
+//! that the lending protocol reads into an account owned by this program, so local tests
+//! can move prices around freely.
+
+// Anchor 0.31's #[program] expands to AccountInfo::realloc, deprecated since solana-account-info 2.3
+#![allow(deprecated)]
+
+use anchor_lang::prelude::*;
+
+declare_id!("5FD5R6bqrxTJZc3AH9ynG4WyZpNesu6AefKdiR3zmD9h");
+
+/// Size of a Pyth v2 price account
+pub const PRICE_ACCOUNT_LEN: usize = 3312;
+
+const MAGIC: u32 = 0xa1b2_c3d4;
+const VERSION: u32 = 2;
+const PRICE_ACCOUNT_TYPE: u32 = 3;
+
+#[program]
+pub mod mock_oracle {
+    use super::*;
+
+    /// Publishes `price` with the given exponent, confidence and status (1 = trading),
+    /// timestamped with the current cluster time
+    pub fn set_price(ctx: Context<SetPrice>, price: i64, expo: i32, conf: u64, status: u32) -> Result<()> {
+        let clock = Clock::get()?;
+        let mut data = ctx.accounts.price_account.try_borrow_mut_data()?;
+        require!(data.len() >= PRICE_ACCOUNT_LEN, MockOracleError::AccountTooSmall);
+
+        data[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+        data[4..8].copy_from_slice(&VERSION.to_le_bytes());
+        data[8..12].copy_from_slice(&PRICE_ACCOUNT_TYPE.to_le_bytes());
+        data[12..16].copy_from_slice(&(PRICE_ACCOUNT_LEN as u32).to_le_bytes());
+        data[20..24].copy_from_slice(&expo.to_le_bytes());
+        data[32..40].copy_from_slice(&clock.slot.to_le_bytes());
+        data[96..104].copy_from_slice(&clock.unix_timestamp.to_le_bytes());
+        // Aggregate price info
+        data[208..216].copy_from_slice(&price.to_le_bytes());
+        data[216..224].copy_from_slice(&conf.to_le_bytes());
+        data[224..228].copy_from_slice(&status.to_le_bytes());
+        data[232..240].copy_from_slice(&clock.slot.to_le_bytes());
+
+        msg!("Price set to {}e{} +/- {}", price, expo, conf);
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct SetPrice<'info> {
+    #[account(mut, owner = crate::ID)]
+    /// CHECK: Raw price account, created by the client with this program as owner
+    pub price_account: AccountInfo<'info>,
+}
+
+#[error_code]
+pub enum MockOracleError {
+    #[msg("Price account is smaller than a Pyth price account")]
+    AccountTooSmall,
+}
@@ -0,0 +1,227 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program, BN } from "@coral-xyz/anchor";
+import { LendingProtocol } from "../target/types/lending_protocol";
+import {
+  Keypair,
+  PublicKey,
+  SystemProgram,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+} from "@solana/web3.js";
+import {
+  createMint,
+  createAccount,
+  getAccount,
+  getAssociatedTokenAddressSync,
+  mintTo,
+  TOKEN_PROGRAM_ID,
+} from "@solana/spl-token";
+import { assert } from "chai";
+
+const sleep = (ms: number) => new Promise((resolve) => setTimeout(resolve, ms));
+
+describe("auction", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.LendingProtocol as Program<LendingProtocol>;
+  const admin = (provider.wallet as anchor.Wallet).payer;
+
+  const lender = Keypair.generate();
+  const borrower = Keypair.generate();
+  const bidder = Keypair.generate();
+
+  let loanMint: PublicKey;
+  let collateralMint: PublicKey;
+  let lenderLoanAccount: PublicKey;
+  let borrowerLoanAccount: PublicKey;
+  let borrowerCollateralAccount: PublicKey;
+  let bidderLoanAccount: PublicKey;
+  let bidderCollateralAccount: PublicKey;
+  let loanInfo: PublicKey;
+  let openLoan: PublicKey;
+  let collateralVault: PublicKey;
+  let auction: PublicKey;
+
+  const loanAmount = new BN(1_000_000);
+  const collateralAmount = new BN(2_000_000);
+
+  const pda = (seeds: Buffer[]) =>
+    PublicKey.findProgramAddressSync(seeds, program.programId)[0];
+
+  async function airdrop(to: PublicKey) {
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(to, 2_000_000_000),
+      "confirmed"
+    );
+  }
+
+  before(async () => {
+    await Promise.all([lender, borrower, bidder].map((k) => airdrop(k.publicKey)));
+
+    const configPda = pda([Buffer.from("protocol_config")]);
+    if ((await provider.connection.getAccountInfo(configPda)) === null) {
+      await program.methods.initialize(admin.publicKey).rpc();
+    }
+
+    loanMint = await createMint(provider.connection, admin, admin.publicKey, null, 6);
+    collateralMint = await createMint(provider.connection, admin, admin.publicKey, null, 6);
+
+    lenderLoanAccount = await createAccount(provider.connection, lender, loanMint, lender.publicKey);
+    borrowerCollateralAccount = await createAccount(provider.connection, borrower, collateralMint, borrower.publicKey);
+    bidderLoanAccount = await createAccount(provider.connection, bidder, loanMint, bidder.publicKey);
+    bidderCollateralAccount = await createAccount(provider.connection, bidder, collateralMint, bidder.publicKey);
+    borrowerLoanAccount = getAssociatedTokenAddressSync(loanMint, borrower.publicKey);
+
+    await mintTo(provider.connection, admin, loanMint, lenderLoanAccount, admin, loanAmount.toNumber());
+    await mintTo(provider.connection, admin, loanMint, bidderLoanAccount, admin, 10 * loanAmount.toNumber());
+    await mintTo(provider.connection, admin, collateralMint, borrowerCollateralAccount, admin, collateralAmount.toNumber());
+
+    const offerCounter = pda([Buffer.from("offer_counter"), lender.publicKey.toBuffer()]);
+    const offerId = (await provider.connection.getAccountInfo(offerCounter)) === null
+      ? new BN(0)
+      : (await program.account.offerCounter.fetch(offerCounter)).nextOfferId;
+    loanInfo = pda([
+      Buffer.from("loan_info"),
+      lender.publicKey.toBuffer(),
+      loanMint.toBuffer(),
+      offerId.toArrayLike(Buffer, "le", 8),
+    ]);
+
+    // A one second loan without price checks, so it is overdue right away
+    const now = Math.floor(Date.now() / 1000);
+    await program.methods
+      .createLoan(
+        loanAmount,
+        collateralAmount,
+        500,
+        new BN(1),
+        new BN(0),
+        new BN(now + 3600),
+        loanAmount,
+        loanAmount,
+        false,
+        0,
+        0
+      )
+      .accountsPartial({
+        loanInfo,
+        lender: lender.publicKey,
+        lenderTokenAccount: lenderLoanAccount,
+        loanTokenMint: loanMint,
+        acceptedTokenMint: collateralMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([lender])
+      .rpc();
+
+    openLoan = pda([Buffer.from("open_loan"), loanInfo.toBuffer(), borrower.publicKey.toBuffer()]);
+    collateralVault = pda([
+      Buffer.from("collateral_vault"),
+      loanInfo.toBuffer(),
+      borrower.publicKey.toBuffer(),
+    ]);
+    auction = pda([Buffer.from("auction"), openLoan.toBuffer()]);
+    await program.methods
+      .takeLoan(loanAmount)
+      .accountsPartial({
+        loanInfo,
+        openLoan,
+        borrower: borrower.publicKey,
+        lender: lender.publicKey,
+        borrowerCollateralTokenAccount: borrowerCollateralAccount,
+        collateralTokenAccount: pda([
+          Buffer.from("collateral_token_account"),
+          collateralVault.toBuffer(),
+        ]),
+        borrowerLoanTokenAccount: borrowerLoanAccount,
+        solEscrow: null,
+        unwrapAccount: null,
+        loanPriceFeed: null,
+        collateralPriceFeed: null,
+        loanTokenMint: loanMint,
+        collateralTokenMint: collateralMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        instructionsSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+      } as any)
+      .signers([borrower])
+      .rpc();
+
+    await sleep(3000);
+  });
+
+  it("lets anyone start an auction for an overdue loan", async () => {
+    await program.methods
+      .startAuction()
+      .accountsPartial({
+        auction,
+        openLoan,
+        loanInfo,
+        collateralVault,
+        loanPriceFeed: null,
+        collateralPriceFeed: null,
+        loanTokenMint: loanMint,
+        collateralTokenMint: collateralMint,
+        payer: bidder.publicKey,
+        borrower: borrower.publicKey,
+      })
+      .signers([bidder])
+      .rpc();
+
+    const loan = await program.account.openLoan.fetch(openLoan);
+    assert.ok("defaulted" in loan.status, "Loan should be defaulted");
+
+    // The lot starts at the default 50% premium over what is owed
+    const state = await program.account.auction.fetch(auction);
+    assert.ok(state.amountOwed.gte(loanAmount));
+    assert.equal(
+      state.startPrice.toString(),
+      state.amountOwed.muln(15_000).divn(10_000).toString()
+    );
+  });
+
+  it("pays the lender what is owed and the borrower the surplus", async () => {
+    const state = await program.account.auction.fetch(auction);
+    const lenderBefore = await getAccount(provider.connection, lenderLoanAccount);
+    const borrowerBefore = await getAccount(provider.connection, borrowerLoanAccount);
+
+    await program.methods
+      .bid(state.startPrice)
+      .accountsPartial({
+        auction,
+        openLoan,
+        loanInfo,
+        collateralVault,
+        collateralTokenAccount: pda([
+          Buffer.from("collateral_token_account"),
+          collateralVault.toBuffer(),
+        ]),
+        solEscrow: null,
+        bidderLoanTokenAccount: bidderLoanAccount,
+        bidderCollateralTokenAccount: bidderCollateralAccount,
+        lenderTokenAccount: lenderLoanAccount,
+        borrowerLoanTokenAccount: borrowerLoanAccount,
+        loanTokenMint: loanMint,
+        collateralTokenMint: collateralMint,
+        bidder: bidder.publicKey,
+        lender: lender.publicKey,
+        borrower: borrower.publicKey,
+        startedBy: bidder.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([bidder])
+      .rpc();
+
+    const lenderAfter = await getAccount(provider.connection, lenderLoanAccount);
+    const borrowerAfter = await getAccount(provider.connection, borrowerLoanAccount);
+    assert.equal(
+      (lenderAfter.amount - lenderBefore.amount).toString(),
+      state.amountOwed.toString()
+    );
+    assert.ok(borrowerAfter.amount > borrowerBefore.amount, "Borrower should get the surplus");
+
+    const seized = await getAccount(provider.connection, bidderCollateralAccount);
+    assert.equal(seized.amount.toString(), collateralAmount.toString());
+    assert.isNull(await provider.connection.getAccountInfo(auction));
+  });
+});
@@ -0,0 +1,298 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program, BN } from "@coral-xyz/anchor";
+import { LendingProtocol } from "../target/types/lending_protocol";
+import { MockOracle } from "../target/types/mock_oracle";
+import {
+  Keypair,
+  PublicKey,
+  SystemProgram,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+} from "@solana/web3.js";
+import {
+  createMint,
+  createAccount,
+  getAccount,
+  getAssociatedTokenAddressSync,
+  mintTo,
+  TOKEN_PROGRAM_ID,
+} from "@solana/spl-token";
+import { assert } from "chai";
+
+// Size of a Pyth v2 price account
+const PRICE_ACCOUNT_LEN = 3312;
+const PYTH_STATUS_TRADING = 1;
+// Repay as much as the close factor allows
+const MAX_REPAY = new BN("18446744073709551615");
+
+describe("liquidate", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.LendingProtocol as Program<LendingProtocol>;
+  const oracleProgram = anchor.workspace.MockOracle as Program<MockOracle>;
+  const admin = (provider.wallet as anchor.Wallet).payer;
+
+  const lender = Keypair.generate();
+  const borrower = Keypair.generate();
+  const liquidator = Keypair.generate();
+  const loanOracle = Keypair.generate();
+  const collateralOracle = Keypair.generate();
+
+  let loanMint: PublicKey;
+  let collateralMint: PublicKey;
+  let lenderLoanAccount: PublicKey;
+  let borrowerCollateralAccount: PublicKey;
+  let liquidatorLoanAccount: PublicKey;
+  let liquidatorCollateralAccount: PublicKey;
+  let loanInfo: PublicKey;
+  let openLoan: PublicKey;
+
+  const loanAmount = new BN(1_000_000); // 1 token with 6 decimals
+  const collateralAmount = new BN(200_000_000); // 0.2 token with 9 decimals
+
+  const pda = (seeds: Buffer[]) =>
+    PublicKey.findProgramAddressSync(seeds, program.programId)[0];
+
+  async function airdrop(to: PublicKey) {
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(to, 2_000_000_000),
+      "confirmed"
+    );
+  }
+
+  async function setPrice(oracle: PublicKey, price: number, conf = 0) {
+    await oracleProgram.methods
+      .setPrice(new BN(price), -8, new BN(conf), PYTH_STATUS_TRADING)
+      .accounts({ priceAccount: oracle })
+      .rpc();
+  }
+
+  async function createOracle(oracle: Keypair, price: number) {
+    const lamports =
+      await provider.connection.getMinimumBalanceForRentExemption(
+        PRICE_ACCOUNT_LEN
+      );
+    const tx = new anchor.web3.Transaction().add(
+      SystemProgram.createAccount({
+        fromPubkey: admin.publicKey,
+        newAccountPubkey: oracle.publicKey,
+        lamports,
+        space: PRICE_ACCOUNT_LEN,
+        programId: oracleProgram.programId,
+      })
+    );
+    await provider.sendAndConfirm(tx, [oracle]);
+    await setPrice(oracle.publicKey, price);
+  }
+
+  // Oracles of the loan mint, then of the collateral mint
+  function oracleAccounts() {
+    return [loanOracle, collateralOracle].map((oracle) => ({
+      pubkey: oracle.publicKey,
+      isSigner: false,
+      isWritable: false,
+    }));
+  }
+
+  function liquidateAccounts() {
+    const collateralVault = pda([
+      Buffer.from("collateral_vault"),
+      loanInfo.toBuffer(),
+      borrower.publicKey.toBuffer(),
+    ]);
+    return {
+      openLoan,
+      loanInfo,
+      collateralVault,
+      collateralTokenAccount: pda([
+        Buffer.from("collateral_token_account"),
+        collateralVault.toBuffer(),
+      ]),
+      solEscrow: null,
+      loanPriceFeed: pda([Buffer.from("price_feed"), loanMint.toBuffer()]),
+      collateralPriceFeed: pda([
+        Buffer.from("price_feed"),
+        collateralMint.toBuffer(),
+      ]),
+      liquidatorLoanTokenAccount: liquidatorLoanAccount,
+      liquidatorCollateralTokenAccount: liquidatorCollateralAccount,
+      lenderTokenAccount: lenderLoanAccount,
+      treasuryTokenAccount: pda([Buffer.from("treasury"), loanMint.toBuffer()]),
+      loanTokenMint: loanMint,
+      collateralTokenMint: collateralMint,
+      liquidator: liquidator.publicKey,
+      lender: lender.publicKey,
+      borrower: borrower.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+    };
+  }
+
+  before(async () => {
+    await Promise.all([lender, borrower, liquidator].map((k) => airdrop(k.publicKey)));
+
+    const configPda = pda([Buffer.from("protocol_config")]);
+    if ((await provider.connection.getAccountInfo(configPda)) === null) {
+      await program.methods.initialize(admin.publicKey).rpc();
+    }
+
+    loanMint = await createMint(provider.connection, admin, admin.publicKey, null, 6);
+    collateralMint = await createMint(provider.connection, admin, admin.publicKey, null, 9);
+
+    lenderLoanAccount = await createAccount(provider.connection, lender, loanMint, lender.publicKey);
+    borrowerCollateralAccount = await createAccount(provider.connection, borrower, collateralMint, borrower.publicKey);
+    liquidatorLoanAccount = await createAccount(provider.connection, liquidator, loanMint, liquidator.publicKey);
+    liquidatorCollateralAccount = await createAccount(provider.connection, liquidator, collateralMint, liquidator.publicKey);
+
+    await mintTo(provider.connection, admin, loanMint, lenderLoanAccount, admin, loanAmount.toNumber());
+    await mintTo(provider.connection, admin, loanMint, liquidatorLoanAccount, admin, 2 * loanAmount.toNumber());
+    await mintTo(provider.connection, admin, collateralMint, borrowerCollateralAccount, admin, collateralAmount.toNumber());
+
+    // Loan token at $1, collateral at $10, so the loan starts at 50% LTV
+    await createOracle(loanOracle, 100_000_000);
+    await createOracle(collateralOracle, 1_000_000_000);
+    for (const [mint, oracle] of [
+      [loanMint, loanOracle.publicKey],
+      [collateralMint, collateralOracle.publicKey],
+    ]) {
+      await program.methods
+        .setPriceFeed()
+        .accounts({ mint })
+        .remainingAccounts([{ pubkey: oracle, isSigner: false, isWritable: false }])
+        .rpc();
+    }
+
+    const offerCounter = pda([Buffer.from("offer_counter"), lender.publicKey.toBuffer()]);
+    const offerId = (await provider.connection.getAccountInfo(offerCounter)) === null
+      ? new BN(0)
+      : (await program.account.offerCounter.fetch(offerCounter)).nextOfferId;
+    loanInfo = pda([
+      Buffer.from("loan_info"),
+      lender.publicKey.toBuffer(),
+      loanMint.toBuffer(),
+      offerId.toArrayLike(Buffer, "le", 8),
+    ]);
+
+    const now = Math.floor(Date.now() / 1000);
+    await program.methods
+      .createLoan(
+        loanAmount,
+        collateralAmount,
+        500,
+        new BN(60 * 60 * 24 * 30),
+        new BN(0),
+        new BN(now + 3600),
+        loanAmount,
+        loanAmount,
+        false,
+        6_000,
+        8_000
+      )
+      .accountsPartial({
+        loanInfo,
+        lender: lender.publicKey,
+        lenderTokenAccount: lenderLoanAccount,
+        loanTokenMint: loanMint,
+        acceptedTokenMint: collateralMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([lender])
+      .rpc();
+
+    openLoan = pda([Buffer.from("open_loan"), loanInfo.toBuffer(), borrower.publicKey.toBuffer()]);
+    const collateralVault = pda([
+      Buffer.from("collateral_vault"),
+      loanInfo.toBuffer(),
+      borrower.publicKey.toBuffer(),
+    ]);
+    await program.methods
+      .takeLoan(loanAmount)
+      .accountsPartial({
+        loanInfo,
+        openLoan,
+        borrower: borrower.publicKey,
+        lender: lender.publicKey,
+        borrowerCollateralTokenAccount: borrowerCollateralAccount,
+        collateralTokenAccount: pda([
+          Buffer.from("collateral_token_account"),
+          collateralVault.toBuffer(),
+        ]),
+        borrowerLoanTokenAccount: getAssociatedTokenAddressSync(loanMint, borrower.publicKey),
+        solEscrow: null,
+        unwrapAccount: null,
+        loanPriceFeed: pda([Buffer.from("price_feed"), loanMint.toBuffer()]),
+        collateralPriceFeed: pda([Buffer.from("price_feed"), collateralMint.toBuffer()]),
+        loanTokenMint: loanMint,
+        collateralTokenMint: collateralMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        instructionsSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+      } as any)
+      .remainingAccounts(oracleAccounts())
+      .signers([borrower])
+      .rpc();
+  });
+
+  it("rejects liquidating a healthy loan", async () => {
+    try {
+      await program.methods
+        .liquidate(MAX_REPAY)
+        .accountsPartial(liquidateAccounts())
+        .remainingAccounts(oracleAccounts())
+        .signers([liquidator])
+        .rpc();
+      assert.fail("liquidation should have failed");
+    } catch (err) {
+      assert.include(err.toString(), "LoanNotLiquidatable");
+    }
+  });
+
+  it("rejects prices with a wide confidence interval", async () => {
+    // Collateral at $5 +/- $1 would make the loan liquidatable, but the price is too uncertain
+    await setPrice(collateralOracle.publicKey, 500_000_000, 100_000_000);
+    try {
+      await program.methods
+        .liquidate(MAX_REPAY)
+        .accountsPartial(liquidateAccounts())
+        .remainingAccounts(oracleAccounts())
+        .signers([liquidator])
+        .rpc();
+      assert.fail("liquidation should have failed");
+    } catch (err) {
+      assert.include(err.toString(), "PriceConfidenceTooWide");
+    }
+  });
+
+  it("lets anyone liquidate up to the close factor once the collateral price drops", async () => {
+    // Collateral falls to $5, taking the loan to 100% LTV
+    await setPrice(collateralOracle.publicKey, 500_000_000);
+
+    const lenderBefore = await getAccount(provider.connection, lenderLoanAccount);
+    await program.methods
+      .liquidate(MAX_REPAY)
+      .accountsPartial(liquidateAccounts())
+      .remainingAccounts(oracleAccounts())
+      .signers([liquidator])
+      .rpc();
+
+    // With the default 50% close factor half of the debt stays with the borrower
+    const loan = await program.account.openLoan.fetch(openLoan);
+    assert.ok("active" in loan.status, "Loan should stay active");
+    assert.ok(loan.outstandingPrincipal.gt(new BN(0)));
+    assert.ok(loan.outstandingPrincipal.lte(loanAmount.divn(2)));
+
+    const lenderAfter = await getAccount(provider.connection, lenderLoanAccount);
+    const repaid = lenderAfter.amount - lenderBefore.amount;
+    assert.ok(repaid >= BigInt(loanAmount.divn(2).toString()), "Lender should get half the debt");
+
+    // $0.50 of debt at the default 5% bonus buys 0.105 of the $5 collateral
+    const seized = await getAccount(provider.connection, liquidatorCollateralAccount);
+    assert.ok(seized.amount >= BigInt(105_000_000));
+    const vault = await program.account.collateralVault.fetch(
+      liquidateAccounts().collateralVault
+    );
+    assert.equal(
+      (BigInt(vault.amount.toString()) + seized.amount).toString(),
+      collateralAmount.toString()
+    );
+  });
+});